@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use rustymines::engine::{utc_date, Event, Game, Preset};
+
+use crate::theme::Look;
+
+//how long an unlock toast stays on screen
+const TOAST: Duration = Duration::from_secs(4);
+
+//an achievement: id (kept in the storage), name and how to get it
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub text: &'static str,
+}
+
+//built-in achievements, in gallery order
+pub const ALL: [Achievement; 10] = [
+    Achievement { id: "first_win", name: "First win", text: "Win a game" },
+    Achievement { id: "boom", name: "Boom", text: "Lose a game" },
+    Achievement { id: "bad_luck", name: "Bad luck", text: "Hit a mine with the first click" },
+    Achievement { id: "beginner_10", name: "Quick start", text: "Win Beginner in less than 10 s" },
+    Achievement { id: "expert_120", name: "Expert", text: "Win Expert in less than 120 s" },
+    Achievement { id: "no_flags", name: "No flags",
+        text: "Reveal every free cell without a flag, with 10 mines or more" },
+    Achievement { id: "clean_sweep", name: "Clean sweep", text: "Win without taking back a flag" },
+    Achievement { id: "big_opening", name: "Big opening", text: "Open 100 cells with one click" },
+    Achievement { id: "chords", name: "Chord player", text: "Chord 20 times in a game" },
+    Achievement { id: "streak_10", name: "On a roll", text: "Win 10 games in a row" },
+];
+
+//what happened in the game in play
+#[derive(Default)]
+struct Tally {
+    reveals: usize, //cells revealed by the player
+    flagged: bool, //a flag went down
+    unflags: usize,
+    chords: usize,
+    lost: bool, //a practice game lost, until the loss is undone
+}
+
+//unlocked achievements and the counters they need,
+//persisted with the app storage
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Achievements {
+    pub unlocked: BTreeMap<String, String>, //unlock date by achievement id
+    pub wins: u32, //games won, undone moves excluded
+    pub streak: u32, //games won in a row
+    #[serde(skip)]
+    tally: Tally,
+    #[serde(skip)]
+    toasts: Vec<(&'static str, Instant)>, //names of the last unlocks
+}
+
+// the preset a board is, if it is one
+fn preset(game: &Game) -> Option<Preset> {
+    let plain = game.layers == 1 && game.max_mines == 1 && game.cells == game.rows * game.columns;
+    Preset::ALL.into_iter()
+        .find(|p| plain && p.board() == (game.rows, game.columns, game.n_mines))
+}
+
+impl Achievements {
+    fn unlock(&mut self, id: &str) {
+        if self.unlocked.contains_key(id) {
+            return;
+        }
+        let Some(achievement) = ALL.iter().find(|a| a.id == id) else { return };
+        self.unlocked.insert(id.to_string(), utc_date(SystemTime::now()));
+        self.toasts.push((achievement.name, Instant::now()));
+    }
+
+    // follow the events of the game in play, time is its clock
+    // the events carry what changes during the game, the game only
+    // gives its board
+    pub fn handle(&mut self, events: &[Event], game: &Game, time: f64) {
+        for &event in events {
+            match event {
+                Event::Start => {
+                    //a practice loss left as it is ends the streak
+                    if self.tally.lost {
+                        self.streak = 0;
+                    }
+                    self.tally = Tally::default();
+                },
+                Event::Reveal(_) => self.tally.reveals += 1,
+                Event::Opening(cells) if cells >= 100 => self.unlock("big_opening"),
+                Event::Opening(_) => {},
+                Event::Flag(_) => self.tally.flagged = true,
+                Event::Unflag(_) => self.tally.unflags += 1,
+                Event::Chord(_) => {
+                    self.tally.chords += 1;
+                    if self.tally.chords >= 20 {
+                        self.unlock("chords");
+                    }
+                },
+                //the game is as good as won, the flags left are a formality
+                Event::Cleared(undone) => {
+                    if !undone && !self.tally.flagged && game.n_mines >= 10 {
+                        self.unlock("no_flags");
+                    }
+                },
+                Event::Win(undone) => self.win(undone, game, time),
+                Event::Loss(practice) => {
+                    if practice {
+                        self.tally.lost = true;
+                    } else {
+                        self.streak = 0;
+                    }
+                    self.unlock("boom");
+                    if self.tally.reveals == 1 {
+                        self.unlock("bad_luck");
+                    }
+                },
+                Event::Undo => self.tally.lost = false,
+            }
+        }
+    }
+
+    // wins with undone moves don't count
+    fn win(&mut self, undone: bool, game: &Game, time: f64) {
+        if undone {
+            return;
+        }
+
+        self.wins += 1;
+        self.streak += 1;
+        self.unlock("first_win");
+        if self.streak >= 10 {
+            self.unlock("streak_10");
+        }
+        match preset(game) {
+            Some(Preset::Beginner) if time < 10. => self.unlock("beginner_10"),
+            Some(Preset::Expert) if time < 120. => self.unlock("expert_120"),
+            _ => {},
+        }
+        if self.tally.unflags == 0 {
+            self.unlock("clean_sweep");
+        }
+    }
+
+    // the unlock toasts in the corner, while they last
+    pub fn toasts(&mut self, ctx: &egui::Context, look: &Look) {
+        self.toasts.retain(|(_, at)| at.elapsed() < TOAST);
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::Vec2::new(-10., -10.))
+            .show(ctx, |ui| {
+                for (name, _) in &self.toasts {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(look.theme.yellow, "Achievement unlocked");
+                        ui.label(*name);
+                    });
+                }
+            });
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+
+    // gallery window, returns false when it's closed
+    pub fn ui(&mut self, ctx: &egui::Context, look: &Look) -> bool {
+        let mut open = true;
+
+        egui::Window::new("Achievements")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let unlocked = ALL.iter().filter(|a| self.unlocked.contains_key(a.id)).count();
+                ui.label(format!("{} of {} unlocked, {} wins, {} in a row",
+                    unlocked, ALL.len(), self.wins, self.streak));
+
+                ui.separator();
+                egui::Grid::new("achievements").striped(true).show(ui, |ui| {
+                    for achievement in &ALL {
+                        match self.unlocked.get(achievement.id) {
+                            Some(date) => {
+                                ui.colored_label(look.theme.yellow, achievement.name);
+                                ui.label(achievement.text);
+                                ui.label(date);
+                            },
+                            None => {
+                                ui.colored_label(look.theme.overlay0, achievement.name);
+                                ui.colored_label(look.theme.overlay0, achievement.text);
+                                ui.label("locked");
+                            },
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustymines::engine::{parse_board, Move};
+
+    //10 mines over a field that opens with one click in the corner
+    const FIELD: &str = "**********\n..........\n..........\n";
+
+    fn play(achievements: &mut Achievements, game: &mut Game, moves: &[Move]) {
+        for &mv in moves {
+            game.play(|game| game.apply(mv));
+        }
+        let events: Vec<Event> = game.events.drain(..).collect();
+        achievements.handle(&events, game, 1.);
+    }
+
+    #[test]
+    fn undone_practice_loss_keeps_the_streak() {
+        for undo in [false, true] {
+            let mut achievements = Achievements { streak: 3, ..Default::default() };
+            let mut game = parse_board(FIELD).unwrap();
+            game.practice = true;
+            play(&mut achievements, &mut game, &[Move::Reveal(0)]);
+            assert_eq!(game.state, 2);
+            if undo {
+                game.undo();
+            }
+            play(&mut achievements, &mut game, &[]);
+            assert_eq!(achievements.streak, 3);
+
+            achievements.handle(&[Event::Start], &parse_board(FIELD).unwrap(), 0.);
+            assert_eq!(achievements.streak, if undo { 3 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn no_flag_win() {
+        let mut achievements = Achievements::default();
+        let mut game = parse_board(FIELD).unwrap();
+        play(&mut achievements, &mut game, &[Move::Flag(0, false), Move::Reveal(29)]);
+        assert!(!achievements.unlocked.contains_key("no_flags"));
+
+        let mut game = parse_board(FIELD).unwrap();
+        play(&mut achievements, &mut game, &[Move::Reveal(29)]);
+        assert_eq!(game.state, 0);
+        assert!(achievements.unlocked.contains_key("no_flags"));
+    }
+}
@@ -0,0 +1,248 @@
+use rustymines::engine::{check_mines, Game, Preset};
+
+use crate::theme::{Flavour, ThemeFiles};
+
+pub const USAGE: &str = "\
+usage: rustymines [options]
+
+options:
+  --rows <n>          number of rows, 4 to 500
+  --cols <n>          number of columns, 4 to 500
+  --mines <n>         number of mines
+  --preset <name>     beginner, intermediate or expert board
+  --seed <seed>       place the mines from a seed, a number or any text
+  --no-welcome        start playing without the welcome window
+  --theme <name>      a catppuccin flavour or a theme file name
+  --load <file>       play a board file, '.' free, '*' mine, '-' hole
+  --bot-protocol      play headless, with a bot speaking the text protocol
+                      over stdin and stdout
+  --games <n>         number of games the bot plays, the seed counts up
+  --bot <command>     run the bot and print its stats instead of
+                      speaking over stdin and stdout
+  -h, --help          print this help";
+
+//theme chosen on the command line
+pub enum ThemeArg {
+    Flavour(Flavour),
+    File(String), //theme file name in the themes directory
+}
+
+//settings from the command line
+#[derive(Default)]
+pub struct Args {
+    pub rows: Option<usize>,
+    pub columns: Option<usize>,
+    pub n_mines: Option<usize>,
+    pub seed: Option<String>,
+    pub no_welcome: bool,
+    pub theme: Option<ThemeArg>,
+    pub game: Option<Game>, //board loaded from a file
+    pub board: String, //name of the loaded board
+    pub bot_protocol: bool,
+    pub games: Option<usize>, //games the bot plays
+    pub bot: Option<String>, //bot command line
+    pub help: bool,
+}
+
+// the number after a flag
+fn number(flag: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("{} needs a number, not '{}'", flag, value))
+}
+
+// theme by flavour name or theme file name
+fn theme(name: &str) -> Result<ThemeArg, String> {
+    //frappé can be typed without the accent
+    if let Some(flavour) = Flavour::ALL.into_iter()
+        .find(|f| [f.name().to_string(), f.name().replace('é', "e")]
+            .iter().any(|n| n.eq_ignore_ascii_case(name))) {
+        return Ok(ThemeArg::Flavour(flavour));
+    }
+
+    let mut files = ThemeFiles::new("RustyMines");
+    files.refresh();
+    match files.get(name).map(|f| &f.theme) {
+        Some(Ok(_)) => Ok(ThemeArg::File(name.to_string())),
+        Some(Err(e)) => Err(format!("theme {}: {}", name, e)),
+        None => Err(format!("unknown theme '{}', use latte, frappe, macchiato, \
+            mocha or the name of a theme file", name)),
+    }
+}
+
+impl Args {
+    // parse the arguments after the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut this = Self::default();
+        let mut preset = None;
+        let mut load = None;
+
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            if flag == "-h" || flag == "--help" {
+                this.help = true;
+                return Ok(this);
+            }
+            if flag == "--no-welcome" || flag == "--bot-protocol" {
+                let given = match flag.as_str() {
+                    "--no-welcome" => std::mem::replace(&mut this.no_welcome, true),
+                    _ => std::mem::replace(&mut this.bot_protocol, true),
+                };
+                if given {
+                    return Err(format!("{} is given twice", flag));
+                }
+                continue;
+            }
+
+            let value = match flag.as_str() {
+                "--rows" | "--cols" | "--mines" | "--preset" | "--seed" | "--theme" | "--load"
+                    | "--games" | "--bot" =>
+                    args.next().ok_or(format!("{} needs a value", flag))?,
+                _ => return Err(format!("unknown option '{}'", flag)),
+            };
+
+            let given = match flag.as_str() {
+                "--rows" => this.rows.replace(number(&flag, &value)?).is_some(),
+                "--cols" => this.columns.replace(number(&flag, &value)?).is_some(),
+                "--mines" => this.n_mines.replace(number(&flag, &value)?).is_some(),
+                "--preset" => preset.replace(Preset::ALL.into_iter()
+                    .find(|p| p.name() == value)
+                    .ok_or(format!("unknown preset '{}', use beginner, intermediate or expert", value))?)
+                    .is_some(),
+                "--seed" => this.seed.replace(value).is_some(),
+                "--theme" => this.theme.replace(theme(&value)?).is_some(),
+                "--games" => this.games.replace(number(&flag, &value)?).is_some(),
+                "--bot" => this.bot.replace(value).is_some(),
+                _ => load.replace(value).is_some(),
+            };
+            if given {
+                return Err(format!("{} is given twice", flag));
+            }
+        }
+
+        let sized = this.rows.is_some() || this.columns.is_some() || this.n_mines.is_some();
+        if let Some(preset) = preset {
+            if sized {
+                return Err(format!("--preset {} sets the board, it can't be used with --rows, --cols or --mines",
+                    preset.name()));
+            }
+            let (rows, columns, n_mines) = preset.board();
+            (this.rows, this.columns, this.n_mines) = (Some(rows), Some(columns), Some(n_mines));
+        }
+
+        if let Some(path) = load {
+            if sized || preset.is_some() || this.seed.is_some() {
+                return Err(String::from("--load plays the board in the file, it can't be used with \
+                    --rows, --cols, --mines, --preset or --seed"));
+            }
+            this.game = Some(Game::load(&path)?);
+            this.board = std::path::Path::new(&path).file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or(path);
+        }
+
+        if this.bot_protocol {
+            if this.no_welcome || this.theme.is_some() {
+                return Err(String::from("--bot-protocol plays without a window, it can't be used with \
+                    --no-welcome or --theme"));
+            }
+            if this.games == Some(0) {
+                return Err(String::from("--games must be at least 1"));
+            }
+            if this.bot.as_ref().is_some_and(|b| b.trim().is_empty()) {
+                return Err(String::from("--bot needs a command"));
+            }
+        } else if this.games.is_some() || this.bot.is_some() {
+            return Err(String::from("--games and --bot are only used with --bot-protocol"));
+        }
+
+        for (flag, value) in [("--rows", this.rows), ("--cols", this.columns)] {
+            if value.is_some_and(|v| !(4..=500).contains(&v)) {
+                return Err(format!("{} must be between 4 and 500", flag));
+            }
+        }
+
+        Ok(this)
+    }
+
+    // check the mines fit the board, leaving at least a free cell
+    pub fn check_mines(&self, rows: usize, columns: usize) -> Result<(), String> {
+        match self.n_mines {
+            Some(0) => Err(String::from("--mines must be at least 1")),
+            Some(n_mines) => check_mines(n_mines, rows, columns, 1, 1),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn rejected() {
+        for (args, error) in [
+            ("--rows 10 --rows 12", "--rows is given twice"),
+            ("--no-welcome --no-welcome", "--no-welcome is given twice"),
+            ("--seed 1 --seed 2", "--seed is given twice"),
+            ("--preset beginner --preset expert", "--preset is given twice"),
+            ("--preset expert --mines 10",
+                "--preset expert sets the board, it can't be used with --rows, --cols or --mines"),
+            ("--cols 20 --preset beginner",
+                "--preset beginner sets the board, it can't be used with --rows, --cols or --mines"),
+            ("--load board.txt --seed 3", "--load plays the board in the file, it can't be used with \
+                --rows, --cols, --mines, --preset or --seed"),
+            ("--rows 10 --load board.txt", "--load plays the board in the file, it can't be used with \
+                --rows, --cols, --mines, --preset or --seed"),
+            ("--rows 3", "--rows must be between 4 and 500"),
+            ("--cols 501", "--cols must be between 4 and 500"),
+            ("--rows ten", "--rows needs a number, not 'ten'"),
+            ("--mines", "--mines needs a value"),
+            ("--size 10", "unknown option '--size'"),
+            ("--preset huge", "unknown preset 'huge', use beginner, intermediate or expert"),
+            ("--games 3", "--games and --bot are only used with --bot-protocol"),
+            ("--bot-protocol --games 0", "--games must be at least 1"),
+            ("--bot-protocol --no-welcome",
+                "--bot-protocol plays without a window, it can't be used with --no-welcome or --theme"),
+        ] {
+            assert_eq!(parse(args).err().as_deref(), Some(error), "{}", args);
+        }
+    }
+
+    #[test]
+    fn too_many_mines() {
+        let args = parse("--rows 4 --cols 4 --mines 16").unwrap();
+        assert_eq!(args.check_mines(4, 4).err().as_deref(), Some("16 mines don't fit a 4x4 board, use at most 15"));
+        let args = parse("--mines 0").unwrap();
+        assert_eq!(args.check_mines(9, 9).err().as_deref(), Some("--mines must be at least 1"));
+        assert!(parse("--rows 4 --cols 4 --mines 15").unwrap().check_mines(4, 4).is_ok());
+    }
+
+    #[test]
+    fn accepted() {
+        let args = parse("--preset expert").unwrap();
+        assert_eq!((args.rows, args.columns, args.n_mines), (Some(16), Some(30), Some(99)));
+
+        let args = parse("--rows 20 --cols 30 --mines 100 --seed abc --no-welcome --theme frappe").unwrap();
+        assert_eq!((args.rows, args.columns, args.n_mines), (Some(20), Some(30), Some(100)));
+        assert_eq!(args.seed.as_deref(), Some("abc"));
+        assert!(args.no_welcome && !args.bot_protocol);
+        assert!(matches!(args.theme, Some(ThemeArg::Flavour(_))));
+
+        let args = parse("--bot-protocol --games 5 --bot ./bot").unwrap();
+        assert!(args.bot_protocol);
+        assert_eq!((args.games, args.bot.as_deref()), (Some(5), Some("./bot")));
+
+        assert!(parse("--rows 12 -h --rows 13").unwrap().help);
+
+        let path = std::env::temp_dir().join(format!("rustymines-args-{}.txt", std::process::id()));
+        std::fs::write(&path, "*...\n....\n....\n....\n").unwrap();
+        let args = parse(&format!("--load {}", path.display()));
+        std::fs::remove_file(&path).unwrap();
+        let args = args.unwrap();
+        assert_eq!(args.game.map(|g| g.n_mines), Some(1));
+        assert_eq!(args.board, format!("rustymines-args-{}", std::process::id()));
+    }
+}
@@ -0,0 +1,17 @@
+//local http/json game server, see src/server.rs for the api
+use rustymines::server::Server;
+
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or(String::from("127.0.0.1:8080"));
+
+    let server = match Server::new(&addr) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("rustymines-server: {}", e);
+            std::process::exit(1);
+        },
+    };
+
+    println!("rustymines-server listening on http://{}", server.addr());
+    server.run();
+}
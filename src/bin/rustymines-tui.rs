@@ -0,0 +1,410 @@
+//terminal frontend, plays the same games as the gui with the engine rules
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crossterm::{cursor, event, execute, queue, style, terminal};
+use crossterm::event::{Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+use crossterm::style::{Attribute, Color, Stylize};
+
+use rustymines::engine::{get_mask, CellTag, Clock, Game, Shape};
+
+//board shapes without a bitmap file
+const SHAPES: [Shape; 3] = [Shape::Rectangle, Shape::Heart, Shape::Ring];
+
+//fields of the welcome screen
+const FIELDS: [&str; 7] = [
+    "shape", "number of rows", "number of columns", "number of layers",
+    "number of mines", "mines per cell", "practice game",
+];
+
+//screen lines above the board
+const TOP: u16 = 2;
+
+//welcome screen settings, with the gui ranges
+struct Settings {
+    shape: Shape,
+    rows: usize, //4..=500
+    columns: usize, //4..=500
+    layers: usize, //1..=5
+    n_mines: usize, //1..=250_000
+    max_mines: u8, //1..=4
+    practice: bool, //moves can be undone
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Rectangle,
+            rows: 20,
+            columns: 20,
+            layers: 1,
+            n_mines: 75,
+            max_mines: 1,
+            practice: false,
+        }
+    }
+}
+
+impl Settings {
+    fn value(&self, field: usize) -> String {
+        match field {
+            0 => self.shape.name().to_string(),
+            1 => self.rows.to_string(),
+            2 => self.columns.to_string(),
+            3 => self.layers.to_string(),
+            4 => self.n_mines.to_string(),
+            5 => self.max_mines.to_string(),
+            _ => if self.practice { "yes" } else { "no" }.to_string(),
+        }
+    }
+
+    // change a field by delta steps
+    fn change(&mut self, field: usize, delta: i64) {
+        let add = |v: usize, lo: usize, hi: usize| {
+            (v as i64 + delta).clamp(lo as i64, hi as i64) as usize
+        };
+
+        match field {
+            0 => {
+                let i = SHAPES.iter().position(|&s| s == self.shape).unwrap_or(0);
+                self.shape = SHAPES[(i as i64 + delta).rem_euclid(SHAPES.len() as i64) as usize];
+            },
+            1 => self.rows = add(self.rows, 4, 500),
+            2 => self.columns = add(self.columns, 4, 500),
+            3 => self.layers = add(self.layers, 1, 5),
+            4 => self.n_mines = add(self.n_mines, 1, 250_000),
+            5 => self.max_mines = add(self.max_mines.into(), 1, 4) as u8,
+            _ => self.practice = !self.practice,
+        }
+    }
+
+    fn game(&self) -> Game {
+        let mut game = Game::new(self.rows, self.columns, self.layers, self.n_mines, self.max_mines,
+            &get_mask(self.shape, self.rows, self.columns), rand::random());
+        game.practice = self.practice;
+        game
+    }
+}
+
+//terminal app data
+struct Tui {
+    settings: Settings, //welcome screen settings
+    field: usize, //selected welcome field
+    welcome: bool, //display the welcome screen
+    game: Game, //the game in play
+    clock: Clock, //game time
+    paused: bool, //the game is paused and the board hidden
+    cursor: usize, //cursor cell index
+    offset: (usize, usize), //first visible row and column
+    quit: bool,
+}
+
+impl Tui {
+    fn new() -> Self {
+        let settings = Settings::default();
+        let game = settings.game();
+        Self {
+            settings,
+            field: 0,
+            welcome: true,
+            game,
+            clock: Clock::default(),
+            paused: false,
+            cursor: 0,
+            offset: (0, 0),
+            quit: false,
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.game = self.settings.game();
+        //the mines left room for a free cell
+        self.settings.n_mines = self.game.n_mines;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.cursor = 0;
+        self.offset = (0, 0);
+        self.welcome = false;
+    }
+
+    fn playing(&self) -> bool {
+        !self.welcome && !self.paused && self.game.state == 0
+    }
+
+    fn run(&mut self, out: &mut impl Write) -> io::Result<()> {
+        while !self.quit {
+            self.clock.run(!self.welcome && !self.paused && self.game.state == 0);
+            self.draw(out)?;
+
+            //redraw at least twice a second for the timer
+            if event::poll(Duration::from_millis(500))? {
+                self.event(event::read()?);
+            }
+        }
+        Ok(())
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                if self.welcome {
+                    self.welcome_key(key.code);
+                } else {
+                    self.board_key(key.code);
+                }
+            },
+            Event::Mouse(mouse) if self.playing() => {
+                let Some(ind) = self.cell_at(mouse.column, mouse.row) else {
+                    return;
+                };
+                match mouse.kind {
+                    MouseEventKind::Down(MouseButton::Left) => {
+                        self.cursor = ind;
+                        //a flagged cell can be clicked, as in the gui
+                        if matches!(self.game.map[ind].flag, CellTag::UNSELECTED | CellTag::FLAGGED) {
+                            self.game.play(|game| game.reveal(ind));
+                        }
+                    },
+                    MouseEventKind::Down(MouseButton::Right) => {
+                        self.cursor = ind;
+                        self.flag();
+                    },
+                    _ => {},
+                }
+            },
+            //losing the focus pauses the game
+            Event::FocusLost if self.playing() => self.paused = true,
+            _ => {},
+        }
+    }
+
+    fn welcome_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.field = self.field.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.field = (self.field + 1).min(FIELDS.len() - 1),
+            KeyCode::Left | KeyCode::Char('h') => self.settings.change(self.field, -1),
+            KeyCode::Right | KeyCode::Char('l') => self.settings.change(self.field, 1),
+            KeyCode::PageDown => self.settings.change(self.field, -10),
+            KeyCode::PageUp => self.settings.change(self.field, 10),
+            KeyCode::Enter => self.new_game(),
+            KeyCode::Esc => self.welcome = false,
+            KeyCode::Char('q') => self.quit = true,
+            _ => {},
+        }
+    }
+
+    fn board_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => self.welcome = true,
+            KeyCode::Char('n') => self.new_game(),
+            KeyCode::Char('p') if self.game.state == 0 => self.paused = !self.paused,
+            //a lost game can be undone
+            KeyCode::Char('u') if self.game.practice && !self.paused => self.game.undo(),
+            KeyCode::Char('r') if self.game.practice && !self.paused => self.game.redo(),
+            _ if !self.playing() => {},
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(0, -1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(0, 1),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(1, 0),
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                let cursor = self.cursor;
+                if self.game.map[cursor].flag == CellTag::UNSELECTED {
+                    self.game.play(|game| game.reveal(cursor));
+                }
+            },
+            KeyCode::Char('f') => self.flag(),
+            KeyCode::Char('d') => {
+                let cursor = self.cursor;
+                self.game.play(|game| game.chord(cursor));
+            },
+            _ => {},
+        }
+    }
+
+    // flag the cell under the cursor, wrapping back to unflagged
+    fn flag(&mut self) {
+        let cursor = self.cursor;
+        self.game.play(|game| {
+            game.add_flag(cursor, true);
+            game.check_winner();
+        });
+    }
+
+    // move the cursor, left and right go on
+    // to the next layer at the board edges
+    fn move_cursor(&mut self, dc: i64, dr: i64) {
+        let (mut l, r, c) = self.game.position(self.cursor);
+        let r = (r as i64 + dr).clamp(0, self.game.rows as i64 - 1) as usize;
+        let mut c = c as i64 + dc;
+
+        if c < 0 && l > 0 {
+            (l, c) = (l - 1, self.game.columns as i64 - 1);
+        } else if c >= self.game.columns as i64 && l < self.game.layers - 1 {
+            (l, c) = (l + 1, 0);
+        }
+        let c = c.clamp(0, self.game.columns as i64 - 1) as usize;
+
+        self.cursor = self.game.index(l, r, c);
+    }
+
+    // visible board rows and columns in the terminal
+    fn view_size() -> (usize, usize) {
+        let (w, h) = terminal::size().unwrap_or((80, 24));
+        (usize::from(h.saturating_sub(TOP + 1)).max(1), usize::from(w / 2).max(1))
+    }
+
+    // cell index at a screen position, in the layer of the cursor
+    fn cell_at(&self, x: u16, y: u16) -> Option<usize> {
+        let (l, _, _) = self.game.position(self.cursor);
+        let r = usize::from(y.checked_sub(TOP)?) + self.offset.0;
+        let c = usize::from(x / 2) + self.offset.1;
+        (r < self.game.rows && c < self.game.columns).then(|| self.game.index(l, r, c))
+    }
+
+    // text and color of a cell, two columns wide
+    fn cell_text(&self, ind: usize) -> (String, Color) {
+        let cell = &self.game.map[ind];
+        let count = |n: u8| if self.game.max_mines > 1 { n.to_string() } else { " ".to_string() };
+
+        match cell.flag {
+            CellTag::HOLE => ("  ".to_string(), Color::Reset),
+            CellTag::UNSELECTED => ("■ ".to_string(), Color::DarkGrey),
+            CellTag::FLAGGED if self.game.state > 0 && cell.flags != cell.mines =>
+                (format!("x{}", count(cell.flags)), Color::Red),
+            CellTag::FLAGGED if self.game.state > 0 => (format!("F{}", count(cell.flags)), Color::Green),
+            CellTag::FLAGGED => (format!("F{}", count(cell.flags)), Color::Red),
+            CellTag::DEAD => (format!("*{}", count(cell.mines)), Color::Red),
+            CellTag::SELECTED if cell.mines > 0 => (format!("*{}", count(cell.mines)), Color::Reset),
+            CellTag::SELECTED if cell.val == 0 => ("  ".to_string(), Color::Reset),
+            CellTag::SELECTED => {
+                let color = match cell.val {
+                    1 => Color::Blue,
+                    2 => Color::Green,
+                    3 => Color::Red,
+                    4 => Color::DarkBlue,
+                    5 => Color::DarkRed,
+                    6 => Color::DarkCyan,
+                    7 => Color::Magenta,
+                    _ => Color::Grey,
+                };
+                (format!("{} ", cell.val), color)
+            },
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, terminal::BeginSynchronizedUpdate,
+            terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+
+        if self.welcome {
+            self.draw_welcome(out)?;
+        } else {
+            self.draw_game(out)?;
+        }
+
+        queue!(out, terminal::EndSynchronizedUpdate)?;
+        out.flush()
+    }
+
+    fn draw_welcome(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, style::PrintStyledContent("RustyMines".bold()), cursor::MoveToNextLine(2))?;
+
+        for (i, name) in FIELDS.iter().enumerate() {
+            let line = format!("{:<20} < {} >", name, self.settings.value(i));
+            if i == self.field {
+                queue!(out, style::PrintStyledContent(line.reverse()))?;
+            } else {
+                queue!(out, style::Print(line))?;
+            }
+            queue!(out, cursor::MoveToNextLine(1))?;
+        }
+
+        queue!(out, cursor::MoveToNextLine(1), style::Print(
+            "up/down choose, left/right change (page up/down by 10), enter play, esc back, q quit"))
+    }
+
+    fn draw_game(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let game = &self.game;
+        let status = match game.state {
+            1 => "You won!",
+            2 => "You lost!",
+            _ if self.paused => "Paused",
+            _ => "Playing",
+        };
+        let (l, r, c) = game.position(self.cursor);
+        let layer = if game.layers > 1 {
+            format!("  Layer {} of {}", l + 1, game.layers)
+        } else {
+            String::new()
+        };
+
+        queue!(out, style::Print(format!("Mines: {} of {}  Selected {} of {}  Time: {:.0}  {}{}",
+            game.f_mines, game.n_mines, game.selected, game.cells,
+            self.clock.elapsed().as_secs_f64(), status, layer)))?;
+        queue!(out, cursor::MoveTo(0, 1), style::PrintStyledContent(
+            "arrows/hjkl move, space reveal, f flag, d chord, p pause, u/r undo/redo, n new, esc settings, q quit"
+                .with(Color::DarkGrey)))?;
+
+        //the board is hidden while paused
+        if self.paused {
+            return queue!(out, cursor::MoveTo(0, TOP + 1), style::Print("Paused, press p to resume"));
+        }
+
+        //scroll the view to keep the cursor visible
+        let (rows, columns) = Self::view_size();
+        let scroll = |first: usize, pos: usize, len: usize| {
+            first.clamp((pos + 1).saturating_sub(len), pos)
+        };
+        self.offset = (scroll(self.offset.0, r, rows), scroll(self.offset.1, c, columns));
+
+        let game = &self.game;
+        for vr in 0..rows.min(game.rows - self.offset.0) {
+            queue!(out, cursor::MoveTo(0, TOP + vr as u16))?;
+            for vc in 0..columns.min(game.columns - self.offset.1) {
+                let ind = game.index(l, self.offset.0 + vr, self.offset.1 + vc);
+                let (text, color) = self.cell_text(ind);
+
+                let mut text = text.with(color);
+                if game.map[ind].flag == CellTag::DEAD {
+                    text = text.on(Color::DarkRed);
+                }
+                if ind == self.cursor && game.state == 0 {
+                    text = text.attribute(Attribute::Reverse);
+                }
+                queue!(out, style::PrintStyledContent(text))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// give the terminal back to the shell
+fn restore(out: &mut impl Write) -> io::Result<()> {
+    execute!(out, cursor::Show, event::DisableFocusChange,
+        event::DisableMouseCapture, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()
+}
+
+fn main() -> io::Result<()> {
+    let mut out = io::stdout();
+
+    //a panic prints its message on the restored terminal
+    let panic = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore(&mut io::stdout());
+        panic(info);
+    }));
+
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, event::EnableMouseCapture,
+        event::EnableFocusChange, cursor::Hide)?;
+
+    let result = Tui::new().run(&mut out);
+
+    //give the terminal back even if the game failed
+    restore(&mut out)?;
+
+    result
+}
@@ -0,0 +1,279 @@
+//headless games for external players, over a line based text protocol
+//
+//for each game the engine sends:
+//  game <n> of <games>
+//  seed <seed>                      (not sent for boards from a file)
+//  size <rows> <columns> <layers>
+//  mines <mines>
+//  board <flags>                    followed by layers * rows lines of
+//                                   columns cells separated by spaces:
+//                                   # covered, F flagged (F2 two flags...),
+//                                   the neighbour mines of a revealed cell,
+//                                   - hole, and at the end
+//                                   * mine, X the exploded mine
+//the player answers with one command per line, rows and columns count
+//from 0 and the layer is only needed on 3D boards:
+//  reveal <row> <column> [layer]
+//  flag <row> <column> [layer]      add a flag (mines are won flagged)
+//  unflag <row> <column> [layer]
+//  chord <row> <column> [layer]
+//  board                            send the board again
+//  resign                           lose the game
+//  quit                             stop playing
+//a move is answered with the new board, a wrong command with
+//  error <message>
+//and the end of a game with
+//  result <won|lost> time <seconds> moves <moves>
+//after the last game the engine sends the totals and closes
+//  stats games <n> won <n> rate <rate> time <seconds> average <seconds> won_average <seconds>
+//  bye
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+use crate::engine::{CellTag, Game};
+
+//the games a bot plays
+pub struct Match {
+    pub rows: usize,
+    pub columns: usize,
+    pub n_mines: usize,
+    pub seed: u64, //seed of the first game, the next ones count up from it
+    pub games: usize,
+    pub board: Option<Game>, //board from a file, played every game
+}
+
+//totals of the games played
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub games: usize,
+    pub won: usize,
+    pub time: f64, //seconds of all the games
+    pub won_time: f64, //seconds of the won games
+    pub moves: usize,
+}
+
+impl Stats {
+    pub fn rate(&self) -> f64 {
+        if self.games == 0 { 0. } else { self.won as f64 / self.games as f64 }
+    }
+
+    pub fn summary(&self) -> String {
+        let average = |time: f64, games: usize| if games == 0 { 0. } else { time / games as f64 };
+        format!("games {} won {} rate {:.3} time {:.3} average {:.3} won_average {:.3}",
+            self.games, self.won, self.rate(), self.time,
+            average(self.time, self.games), average(self.won_time, self.won))
+    }
+}
+
+//what a command asks for
+pub(crate) enum Reply {
+    Move, //a move was done, send the new board
+    Board,
+    Quit,
+}
+
+// the cell as sent to the player
+pub(crate) fn cell_text(game: &Game, ind: usize) -> String {
+    let cell = &game.map[ind];
+    match cell.flag {
+        CellTag::UNSELECTED => String::from("#"),
+        CellTag::FLAGGED if cell.flags > 1 => format!("F{}", cell.flags),
+        CellTag::FLAGGED => String::from("F"),
+        CellTag::SELECTED if cell.mines > 0 => String::from("*"),
+        CellTag::SELECTED => cell.val.to_string(),
+        CellTag::DEAD => String::from("X"),
+        CellTag::HOLE => String::from("-"),
+    }
+}
+
+fn write_board(game: &Game, output: &mut impl Write) -> io::Result<()> {
+    writeln!(output, "board {}", game.f_mines)?;
+    for start in (0..game.map.len()).step_by(game.columns) {
+        let line: Vec<String> = (start..start + game.columns)
+            .map(|ind| cell_text(game, ind))
+            .collect();
+        writeln!(output, "{}", line.join(" "))?;
+    }
+    Ok(())
+}
+
+// index of the cell at "row column [layer]"
+fn cell_index(game: &Game, args: &[&str]) -> Result<usize, String> {
+    let numbers: Vec<usize> = args.iter()
+        .map(|a| a.parse().map_err(|_| format!("'{}' isn't a number", a)))
+        .collect::<Result<_, _>>()?;
+
+    let (r, c, l) = match numbers[..] {
+        [r, c] => (r, c, 0),
+        [r, c, l] => (r, c, l),
+        _ => return Err(String::from("expected a row, a column and on 3D boards a layer")),
+    };
+    if r >= game.rows || c >= game.columns || l >= game.layers {
+        return Err(format!("{} {} {} is outside the board", r, c, l));
+    }
+
+    let ind = game.index(l, r, c);
+    if game.map[ind].flag == CellTag::HOLE {
+        return Err(format!("{} {} {} is a hole", r, c, l));
+    }
+    Ok(ind)
+}
+
+// run a command of the player
+pub(crate) fn command(game: &mut Game, line: &str) -> Result<Reply, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words[..] {
+        ["quit"] => Ok(Reply::Quit),
+        ["board"] => Ok(Reply::Board),
+        ["resign"] => {
+            game.state = 2; //looser
+            Ok(Reply::Move)
+        },
+        [cmd @ ("reveal" | "flag" | "unflag" | "chord"), ref pos @ ..] => {
+            let ind = cell_index(game, pos)?;
+            let flag = game.map[ind].flag;
+            match cmd {
+                "reveal" if flag == CellTag::UNSELECTED => game.reveal(ind),
+                "reveal" if flag == CellTag::FLAGGED => return Err(String::from("the cell is flagged")),
+                "flag" | "unflag" if flag == CellTag::SELECTED => return Err(String::from("the cell is revealed")),
+                "flag" if game.f_mines >= game.n_mines => return Err(String::from("all the flags are used")),
+                "flag" => {
+                    game.add_flag(ind, false);
+                    game.check_winner();
+                },
+                "unflag" => game.remove_flag(ind),
+                "chord" if flag == CellTag::SELECTED => game.chord(ind),
+                "chord" => return Err(String::from("the cell isn't revealed")),
+                _ => return Err(String::from("the cell is revealed")),
+            }
+            Ok(Reply::Move)
+        },
+        _ => Err(format!("unknown command '{}'", line.trim())),
+    }
+}
+
+// play the games of the match with a player reading output and writing input
+// a player that closes its input ends the match, the stats count the games
+// finished until then
+pub fn run(settings: &Match, input: impl BufRead, mut output: impl Write) -> io::Result<Stats> {
+    let mut stats = Stats::default();
+    let closed = |e: io::Error| if e.kind() == io::ErrorKind::BrokenPipe { Ok(()) } else { Err(e) };
+
+    play(settings, input, &mut output, &mut stats).or_else(closed)?;
+    writeln!(output, "stats {}", stats.summary())
+        .and_then(|_| writeln!(output, "bye"))
+        .and_then(|_| output.flush())
+        .or_else(closed)?;
+
+    Ok(stats)
+}
+
+fn play(settings: &Match, input: impl BufRead, output: &mut impl Write, stats: &mut Stats) -> io::Result<()> {
+    let mut lines = input.lines();
+
+    'games: for n in 0..settings.games {
+        let mut game = match &settings.board {
+            Some(board) => board.clone(),
+            None => Game::new(settings.rows, settings.columns, 1, settings.n_mines, 1,
+                &vec![true; settings.rows * settings.columns],
+                settings.seed.wrapping_add(n as u64)),
+        };
+
+        writeln!(output, "game {} of {}", n + 1, settings.games)?;
+        if let Some(seed) = game.seed {
+            writeln!(output, "seed {}", seed)?;
+        }
+        writeln!(output, "size {} {} {}", game.rows, game.columns, game.layers)?;
+        writeln!(output, "mines {}", game.n_mines)?;
+        write_board(&game, output)?;
+        output.flush()?;
+
+        let start = Instant::now();
+        let mut moves = 0;
+        while game.state == 0 {
+            //the player went away
+            let Some(line) = lines.next() else { break 'games };
+
+            match command(&mut game, &line?) {
+                Ok(Reply::Quit) => break 'games,
+                Ok(Reply::Move) => {
+                    moves += 1;
+                    write_board(&game, output)?;
+                },
+                Ok(Reply::Board) => write_board(&game, output)?,
+                Err(e) => writeln!(output, "error {}", e)?,
+            }
+            output.flush()?;
+        }
+
+        let time = start.elapsed().as_secs_f64();
+        let won = game.state == 1;
+        writeln!(output, "result {} time {:.3} moves {}", if won { "won" } else { "lost" }, time, moves)?;
+
+        stats.games += 1;
+        stats.time += time;
+        stats.moves += moves;
+        if won {
+            stats.won += 1;
+            stats.won_time += time;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::parse_board;
+
+    fn settings(games: usize) -> Match {
+        let board = parse_board("*...\n....\n....\n....\n").unwrap();
+        Match { rows: 4, columns: 4, n_mines: 1, seed: 0, games, board: Some(board) }
+    }
+
+    //a player that stops reading after a few bytes
+    struct Closed(usize);
+
+    impl Write for Closed {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0 < buf.len() {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn transcript() {
+        let input = "reveal 3 3\nflag 0 0\nboard\nbogus\nflag 9 9\nresign\n";
+        let mut output = Vec::new();
+        let stats = run(&settings(3), input.as_bytes(), &mut output).unwrap();
+        assert_eq!((stats.games, stats.won, stats.moves), (2, 1, 3));
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[..4], ["game 1 of 3", "size 4 4 1", "mines 1", "board 0"]);
+        assert!(lines.iter().any(|l| l.starts_with("result won time")));
+        assert!(lines.contains(&"game 2 of 3"));
+        assert!(lines.contains(&"error unknown command 'bogus'"));
+        assert!(lines.contains(&"error 9 9 0 is outside the board"));
+        assert!(lines.iter().any(|l| l.starts_with("result lost time")));
+        //the input ended in the third game, it doesn't count
+        assert!(lines.contains(&"game 3 of 3"));
+        assert!(lines[lines.len() - 2].starts_with("stats games 2 won 1 "));
+        assert_eq!(lines[lines.len() - 1], "bye");
+    }
+
+    #[test]
+    fn closed_output_ends_the_match() {
+        let input = "reveal 3 3\nflag 0 0\nreveal 3 3\n";
+        let stats = run(&settings(2), input.as_bytes(), Closed(200)).unwrap();
+        assert_eq!((stats.games, stats.won), (1, 1));
+    }
+}
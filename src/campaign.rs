@@ -0,0 +1,159 @@
+//campaign: levels played in order, each one unlocks the next
+//
+//the levels are in a toml file, one [[level]] table each:
+//  name = "Heart"
+//  rows = 12
+//  columns = 12
+//  mines = 20
+//  shape = "heart"        # optional: rectangle, heart or ring
+//  layers = 1             # optional, 1 to 5
+//  max_mines = 1          # optional, mines per cell, 1 to 4
+//  time = 120.0           # optional target, win in less seconds
+//  rate = 0.8             # optional target, win at 3BV per second or more
+//a level is passed with a win that meets its targets
+use serde::Deserialize;
+
+use crate::engine::{check_mask_mines, get_mask, Game, Shape};
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    pub name: String,
+    pub rows: usize,
+    pub columns: usize,
+    pub mines: usize,
+    #[serde(default)]
+    pub shape: Option<String>,
+    #[serde(default = "one")]
+    pub layers: usize,
+    #[serde(default = "one")]
+    pub max_mines: usize,
+    #[serde(default)]
+    pub time: Option<f64>, //target seconds
+    #[serde(default)]
+    pub rate: Option<f64>, //target 3BV per second
+}
+
+#[derive(Deserialize)]
+struct CampaignToml {
+    #[serde(rename = "level", default)]
+    levels: Vec<Level>,
+}
+
+// parse the text of a campaign file
+pub fn parse_campaign(text: &str) -> Result<Vec<Level>, String> {
+    let file: CampaignToml = toml::from_str(text).map_err(|e| e.to_string())?;
+    if file.levels.is_empty() {
+        return Err(String::from("the campaign has no level"));
+    }
+
+    for (n, level) in file.levels.iter().enumerate() {
+        let error = |e: &str| format!("level {} '{}': {}", n + 1, level.name, e);
+        if !(4..=500).contains(&level.rows) || !(4..=500).contains(&level.columns) {
+            return Err(error("rows and columns go from 4 to 500"));
+        }
+        if !(1..=5).contains(&level.layers) {
+            return Err(error("layers go from 1 to 5"));
+        }
+        if !(1..=4).contains(&level.max_mines) {
+            return Err(error("max_mines goes from 1 to 4"));
+        }
+        if level.mines == 0 {
+            return Err(error("a level needs mines"));
+        }
+        //Game::new would play a level with too many mines with less
+        let shape = level.shape().map_err(|e| error(&e))?;
+        check_mask_mines(level.mines, &get_mask(shape, level.rows, level.columns),
+            level.layers, level.max_mines as u8).map_err(|e| error(&e))?;
+        if file.levels[..n].iter().any(|l| l.name == level.name) {
+            return Err(error("the name is taken by another level"));
+        }
+    }
+
+    Ok(file.levels)
+}
+
+impl Level {
+    pub fn shape(&self) -> Result<Shape, String> {
+        match self.shape.as_deref() {
+            None => Ok(Shape::Rectangle),
+            Some(name) => Shape::ALL.into_iter()
+                .find(|s| s.name() == name && *s != Shape::Bitmap)
+                .ok_or_else(|| format!("unknown shape '{}', expected rectangle, heart or ring", name)),
+        }
+    }
+
+    // a board of the level
+    pub fn game(&self, seed: u64) -> Game {
+        let mask = get_mask(self.shape().unwrap_or(Shape::Rectangle), self.rows, self.columns);
+        Game::new(self.rows, self.columns, self.layers, self.mines, self.max_mines as u8, &mask, seed)
+    }
+
+    // the board and the targets, "16x16, 40 mines, under 90s"
+    pub fn text(&self) -> String {
+        let mut text = format!("{}x{}", self.rows, self.columns);
+        if self.layers > 1 {
+            text += &format!("x{}", self.layers);
+        }
+        if let Some(shape) = &self.shape {
+            text += &format!(" {}", shape);
+        }
+        text += &format!(", {} mines", self.mines);
+        if self.max_mines > 1 {
+            text += &format!(" (up to {} per cell)", self.max_mines);
+        }
+        if let Some(time) = self.time {
+            text += &format!(", under {}s", time);
+        }
+        if let Some(rate) = self.rate {
+            text += &format!(", {} 3BV/s", rate);
+        }
+        text
+    }
+
+    // a finished game meets the targets
+    pub fn passed(&self, game: &Game, time: f64) -> bool {
+        let rate = game.bbbv() as f64 / time.max(0.001);
+        game.state == 1
+            && self.time.is_none_or(|t| time <= t)
+            && self.rate.is_none_or(|r| rate >= r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_campaign_loads() {
+        let levels = parse_campaign(include_str!("../assets/campaign.toml")).expect("a valid built-in campaign");
+        for level in &levels {
+            assert!(level.shape().is_ok(), "{}", level.name);
+            let game = level.game(1);
+            assert_eq!(game.n_mines, level.mines, "{}", level.name);
+            assert_eq!(game.state, 0, "{}", level.name);
+        }
+    }
+
+    #[test]
+    fn mines_fit_the_level() {
+        let level = |mines: usize, extra: &str| format!("[[level]]\nname = \"Full\"\nrows = 4\ncolumns = 4\n\
+            mines = {}\n{}", mines, extra);
+        assert!(parse_campaign(&level(15, "")).is_ok());
+        assert_eq!(parse_campaign(&level(16, "")).err().as_deref(),
+            Some("level 1 'Full': 16 mines don't fit the 16 cells of the board, use at most 15"));
+        assert!(parse_campaign(&level(30, "max_mines = 2\n")).is_ok());
+        assert!(parse_campaign(&level(31, "layers = 2\n")).is_ok());
+        assert!(parse_campaign(&level(32, "layers = 2\n")).is_err());
+
+        //the holes of a ring take no mines
+        let ring = get_mask(Shape::Ring, 4, 4).iter().filter(|&&m| m).count();
+        assert!(ring < 16);
+        assert!(parse_campaign(&level(ring - 1, "shape = \"ring\"\n")).is_ok());
+        assert!(parse_campaign(&level(ring, "shape = \"ring\"\n")).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, HashMap};
+
+use eframe::egui;
+use egui::Color32;
+
+use rustymines::engine::{check_mask_mines, get_mask, CellTag, Game, Move, Shape};
+
+use crate::net::{Event, Link, HOST};
+use crate::theme::Look;
+
+//a player clearing the board with the others
+pub struct Mate {
+    pub name: String,
+    pub cursor: Option<usize>, //cell the player points at
+    pub left: bool, //the connection closed
+}
+
+impl Mate {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), cursor: None, left: false }
+    }
+}
+
+//players clearing one board over the lan, the host owns the board:
+//it plays the moves in the order they arrive and sends them to everyone,
+//a move that doesn't fit the board any more when its turn comes is dropped,
+//like a reveal of a cell another player just flagged or a flag on the flag
+//of another player
+//  client -> host  hello coop <name>, move <move>, cursor <cell>
+//  host -> client  welcome <id>
+//  host -> all     player <id> <name>, left <id>,
+//                  start <seed> <rows> <columns> <mines> <layers> <max mines> <shape>,
+//                  played <id> <move>, cursor <id> <cell>
+pub struct Coop {
+    pub host: bool,
+    pub me: Option<usize>, //my player id, once the host welcomed me
+    pub mates: BTreeMap<usize, Mate>,
+    pub owners: HashMap<usize, usize>, //player that flagged each flagged cell
+    pub started: bool,
+    pub error: Option<String>,
+    link: Link,
+}
+
+// color of a player, for its cursor and its flags
+pub fn mate_color(id: usize, look: &Look) -> Color32 {
+    let theme = &look.theme;
+    let colors = [theme.blue, theme.green, theme.peach, theme.mauve,
+        theme.teal, theme.pink, theme.yellow, theme.sapphire];
+    colors[id % colors.len()]
+}
+
+// the start line of a board, after "start"
+fn start_line(seed: u64, rows: usize, columns: usize, layers: usize,
+    n_mines: usize, max_mines: u8, shape: Shape) -> String {
+    format!("{} {} {} {} {} {} {}", seed, rows, columns, n_mines, layers, max_mines, shape.name())
+}
+
+// the game of a start line, bitmap shapes can't be sent
+fn parse_start(start: &str) -> Result<Game, String> {
+    let words: Vec<&str> = start.split_whitespace().collect();
+    let [ref numbers @ .., shape] = words[..] else { return Err(String::from("the start line is empty")) };
+    let numbers: Vec<u64> = numbers.iter().filter_map(|w| w.parse().ok()).collect();
+    let [seed, rows, columns, n_mines, layers, max_mines] = numbers[..] else {
+        return Err(format!("'{}' isn't a start line", start));
+    };
+    let (rows, columns, layers) = (rows as usize, columns as usize, layers as usize);
+    if !(4..=500).contains(&rows) || !(4..=500).contains(&columns) {
+        return Err(String::from("rows and columns go from 4 to 500"));
+    }
+    if !(1..=5).contains(&layers) {
+        return Err(String::from("layers go from 1 to 5"));
+    }
+    if !(1..=4).contains(&max_mines) {
+        return Err(String::from("mines per cell go from 1 to 4"));
+    }
+    let shape = Shape::ALL.into_iter()
+        .find(|s| s.name() == shape && *s != Shape::Bitmap)
+        .ok_or_else(|| format!("a {} board can't be played in co-op", shape))?;
+
+    let mask = get_mask(shape, rows, columns);
+    check_mask_mines(n_mines as usize, &mask, layers, max_mines as u8)?;
+    Ok(Game::new(rows, columns, layers, n_mines as usize, max_mines as u8, &mask, seed))
+}
+
+impl Coop {
+    // host a co-op game on addr
+    pub fn host(addr: &str, name: &str) -> Result<Self, String> {
+        Ok(Self {
+            host: true,
+            me: Some(HOST),
+            mates: BTreeMap::from([(HOST, Mate::new(name))]),
+            owners: HashMap::new(),
+            started: false,
+            error: None,
+            link: Link::host(addr)?,
+        })
+    }
+
+    // join the co-op game hosted on addr
+    pub fn join(addr: &str, name: &str) -> Result<Self, String> {
+        Ok(Self {
+            host: false,
+            me: None,
+            mates: BTreeMap::new(),
+            owners: HashMap::new(),
+            started: false,
+            error: None,
+            link: Link::join(addr, &format!("hello coop {}", name))?,
+        })
+    }
+
+    // start the game for everyone, only the host can,
+    // settings that don't make a board are refused in the error
+    pub fn start(&mut self, rows: usize, columns: usize, layers: usize,
+        n_mines: usize, max_mines: u8, shape: Shape) -> Option<Game> {
+        let seed: u64 = rand::random();
+        let start = start_line(seed, rows, columns, layers, n_mines, max_mines, shape);
+        let game = self.begin(&start)?;
+        self.link.send(&format!("start {}", start), None);
+        Some(game)
+    }
+
+    // the game of a start line
+    fn begin(&mut self, start: &str) -> Option<Game> {
+        match parse_start(start) {
+            Ok(game) => {
+                self.started = true;
+                self.owners.clear();
+                self.error = None;
+                Some(game)
+            },
+            Err(e) => {
+                self.error = Some(format!("can't start: {}", e));
+                None
+            },
+        }
+    }
+
+    // the move can be played by the player
+    fn allowed(&self, game: &Game, id: usize, mv: Move) -> bool {
+        if game.state != 0 || mv.cell() >= game.map.len() {
+            return false;
+        }
+
+        let flag = game.map[mv.cell()].flag;
+        let own = self.owners.get(&mv.cell()).is_none_or(|&owner| owner == id);
+        match mv {
+            Move::Reveal(_) => flag == CellTag::UNSELECTED,
+            Move::Flag(..) => flag == CellTag::UNSELECTED || (flag == CellTag::FLAGGED && own),
+            Move::Unflag(_) => flag == CellTag::FLAGGED && own,
+            Move::Chord(_) => flag == CellTag::SELECTED,
+        }
+    }
+
+    // play a move of a player, keeping who flagged what
+    fn apply(&mut self, game: &mut Game, id: usize, mv: Move) {
+        game.apply(mv);
+        if let Move::Flag(ind, _) | Move::Unflag(ind) = mv {
+            self.owners.insert(ind, id);
+        }
+        self.owners.retain(|&ind, _| game.map[ind].flag == CellTag::FLAGGED);
+    }
+
+    // my move, the host plays it for everyone
+    pub fn play(&mut self, game: &mut Game, mv: Move) {
+        match self.me {
+            Some(HOST) if self.host => self.host_move(game, HOST, mv),
+            Some(_) => self.link.send(&format!("move {}", mv.text()), None),
+            None => {},
+        }
+    }
+
+    fn host_move(&mut self, game: &mut Game, id: usize, mv: Move) {
+        if self.allowed(game, id, mv) {
+            self.apply(game, id, mv);
+            self.link.send(&format!("played {} {}", id, mv.text()), None);
+        }
+    }
+
+    // tell the others where I point
+    pub fn point(&mut self, cell: Option<usize>) {
+        let Some(me) = self.me.filter(|_| self.started) else { return };
+        let Some(mate) = self.mates.get_mut(&me) else { return };
+        if mate.cursor == cell {
+            return;
+        }
+        mate.cursor = cell;
+
+        let cell = cell.map_or(String::from("none"), |c| c.to_string());
+        if self.host {
+            self.link.send(&format!("cursor {} {}", me, cell), None);
+        } else {
+            self.link.send(&format!("cursor {}", cell), None);
+        }
+    }
+
+    // apply a line from the host, a start gives the game to play
+    fn receive(&mut self, line: &str, game: &mut Game) -> Option<Game> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let id: Option<usize> = rest.split_whitespace().next().and_then(|w| w.parse().ok());
+        let after_id = rest.split_once(' ').map(|(_, r)| r).unwrap_or_default();
+
+        match command {
+            "welcome" => self.me = id,
+            "player" => {
+                self.mates.insert(id?, Mate::new(after_id));
+            },
+            "left" => self.mates.get_mut(&id?)?.left = true,
+            "start" => return self.begin(rest),
+            "played" => {
+                //the host checked the move, every board plays the same moves
+                let mv = Move::parse(after_id)?;
+                if mv.cell() < game.map.len() {
+                    self.apply(game, id?, mv);
+                }
+            },
+            "cursor" => self.mates.get_mut(&id?)?.cursor = after_id.parse().ok(),
+            "error" => self.error = Some(rest.to_string()),
+            _ => {},
+        }
+        None
+    }
+
+    // handle the network events on the shared game,
+    // true if a start replaced the game
+    pub fn poll(&mut self, game: &mut Game) -> bool {
+        let mut start = false;
+        while let Some(event) = self.link.poll() {
+            match event {
+                Event::Line(id, line) if self.host => {
+                    let (command, rest) = line.split_once(' ').unwrap_or((&line, ""));
+                    match command {
+                        "hello" => match rest.strip_prefix("coop ") {
+                            Some(name) => self.hello(id, name),
+                            None => {
+                                self.link.send_to(id, "error the host plays co-op, not a race");
+                                self.link.drop_peer(id);
+                            },
+                        },
+                        "move" if self.started => {
+                            if let Some(mv) = Move::parse(rest) {
+                                self.host_move(game, id, mv);
+                            }
+                        },
+                        "cursor" if self.mates.contains_key(&id) => {
+                            self.link.send(&format!("cursor {} {}", id, rest), Some(id));
+                            self.receive(&format!("cursor {} {}", id, rest), game);
+                        },
+                        _ => {},
+                    }
+                },
+                Event::Line(_, line) => {
+                    //the next moves are on the new game
+                    if let Some(new) = self.receive(&line, game) {
+                        *game = new;
+                        start = true;
+                    }
+                },
+                Event::Closed(id) if self.host => {
+                    if self.mates.contains_key(&id) {
+                        let line = format!("left {}", id);
+                        self.link.send(&line, None);
+                        self.receive(&line, game);
+                    }
+                },
+                Event::Closed(_) => {
+                    self.error.get_or_insert(String::from("the host left the game"));
+                },
+            }
+        }
+        start
+    }
+
+    // a new player, the game waits in the lobby
+    fn hello(&mut self, id: usize, name: &str) {
+        if self.started {
+            self.link.send_to(id, "error the game started already");
+            self.link.drop_peer(id);
+            return;
+        }
+        self.link.send_to(id, &format!("welcome {}", id));
+
+        self.mates.insert(id, Mate::new(name.trim()));
+        let lines: Vec<String> = self.mates.iter()
+            .map(|(id, m)| format!("player {} {}", id, m.name))
+            .collect();
+        for line in lines {
+            self.link.send(&line, None);
+        }
+    }
+
+    // lobby and players window
+    // returns (open, start), closing it leaves the game
+    pub fn ui(&mut self, ctx: &egui::Context, look: &Look, game: &Game, can_start: bool) -> (bool, bool) {
+        let mut open = true;
+        let mut start = false;
+        egui::Window::new("Co-op")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(e) = &self.error {
+                    ui.colored_label(look.theme.red, e);
+                }
+
+                if !self.started {
+                    if self.host {
+                        ui.label("Waiting for players, they join the game with your address.");
+                        if ui.add_enabled(can_start && self.mates.len() > 1,
+                            egui::Button::new("Start with the welcome settings")).clicked() {
+                            start = true;
+                        }
+                    } else {
+                        ui.label(if self.me.is_some() { "Waiting for the host to start." } else { "Connecting..." });
+                    }
+                } else {
+                    ui.label(match game.state {
+                        0 => "Clearing the board together",
+                        1 => "The board is cleared!",
+                        _ => "A mine went off, the game is over for everyone",
+                    });
+                }
+
+                egui::Grid::new("mates").show(ui, |ui| {
+                    for (id, mate) in &self.mates {
+                        let you = if Some(*id) == self.me { " (you)" } else { "" };
+                        ui.colored_label(mate_color(*id, look), format!("\u{25a0} {}{}", mate.name, you));
+
+                        let flags = self.owners.values().filter(|&&owner| owner == *id).count();
+                        ui.label(if mate.left { String::from("left") } else { format!("{} flags", flags) });
+                        ui.end_row();
+                    }
+                });
+            });
+
+        (open, start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustymines::engine::parse_board;
+
+    //a host on a free port and its board, the mine at 0
+    fn host() -> (Coop, Game) {
+        let coop = Coop::host("127.0.0.1:0", "host").unwrap();
+        (coop, parse_board("*...\n....\n....\n....\n").unwrap())
+    }
+
+    #[test]
+    fn flags_belong_to_their_player() {
+        let (mut coop, mut game) = host();
+        assert!(coop.allowed(&game, 1, Move::Flag(6, false)));
+        coop.apply(&mut game, 1, Move::Flag(6, false));
+        assert_eq!(coop.owners.get(&6), Some(&1));
+
+        assert!(!coop.allowed(&game, 2, Move::Unflag(6)));
+        assert!(!coop.allowed(&game, 2, Move::Flag(6, false)));
+        assert!(!coop.allowed(&game, 2, Move::Reveal(6)));
+        assert!(coop.allowed(&game, 1, Move::Unflag(6)));
+
+        //once it's gone the cell is anyone's
+        coop.apply(&mut game, 1, Move::Unflag(6));
+        assert!(coop.owners.is_empty());
+        assert!(coop.allowed(&game, 2, Move::Flag(6, false)));
+    }
+
+    #[test]
+    fn a_cell_is_revealed_once() {
+        let (mut coop, mut game) = host();
+        coop.apply(&mut game, 1, Move::Reveal(5));
+        assert!(!coop.allowed(&game, 2, Move::Reveal(5)));
+        assert!(!coop.allowed(&game, 1, Move::Reveal(5)));
+        assert!(!coop.allowed(&game, 2, Move::Flag(5, false)));
+        assert!(coop.allowed(&game, 2, Move::Chord(5)));
+        assert!(!coop.allowed(&game, 2, Move::Reveal(16)));
+    }
+
+    #[test]
+    fn nothing_after_a_loss() {
+        let (mut coop, mut game) = host();
+        coop.apply(&mut game, 1, Move::Reveal(5));
+        coop.apply(&mut game, 1, Move::Flag(1, false));
+        coop.apply(&mut game, 2, Move::Reveal(0));
+        assert_eq!(game.state, 2);
+        for ind in 0..game.map.len() {
+            for id in [HOST, 1, 2] {
+                for mv in [Move::Reveal(ind), Move::Flag(ind, false), Move::Unflag(ind), Move::Chord(ind)] {
+                    assert!(!coop.allowed(&game, id, mv));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn start_line_round_trip() {
+        let line = start_line(42, 12, 10, 2, 30, 2, Shape::Heart);
+        let game = parse_start(&line).unwrap();
+        assert_eq!((game.rows, game.columns, game.layers), (12, 10, 2));
+        assert_eq!((game.n_mines, game.max_mines), (30, 2));
+        let mask = get_mask(Shape::Heart, 12, 10);
+        assert_eq!(game.map, Game::new(12, 10, 2, 30, 2, &mask, 42).map);
+
+        for (line, error) in [
+            (start_line(1, 9, 9, 1, 10, 1, Shape::Bitmap), "a bitmap board can't be played in co-op"),
+            (start_line(1, 9, 9, 6, 10, 1, Shape::Ring), "layers go from 1 to 5"),
+            (start_line(1, 9, 9, 1, 10, 5, Shape::Ring), "mines per cell go from 1 to 4"),
+            (start_line(1, 3, 9, 1, 2, 1, Shape::Rectangle), "rows and columns go from 4 to 500"),
+            (start_line(1, 4, 4, 1, 16, 1, Shape::Rectangle), "16 mines don't fit the 16 cells of the board, use at most 15"),
+            (String::from("1 9 9 10 rectangle"), "'1 9 9 10 rectangle' isn't a start line"),
+        ] {
+            assert_eq!(parse_start(&line).err().as_deref(), Some(error));
+        }
+    }
+}
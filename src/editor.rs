@@ -0,0 +1,210 @@
+use eframe::egui;
+
+use rustymines::engine::{board_text, CellTag, Game};
+use rustymines::solver;
+
+use crate::{paint_cell, zoom_and_pan};
+use crate::theme::Look;
+
+//board editor for hand-made puzzles: a click places or removes a mine,
+//a right click reveals a free cell at the start, the numbers follow the mines
+pub struct Editor {
+    pub rows: usize,
+    pub columns: usize,
+    mines: Vec<bool>, //mined cells
+    start: Vec<bool>, //free cells revealed at the start
+    board: Game, //the board as it will be played
+    pub path: String, //board file to save to
+    stuck: Vec<bool>, //cells the solver couldn't tell in the last check
+    message: Option<Result<String, String>>, //last check or save
+    pub testing: bool, //the board is being played
+}
+
+impl Editor {
+    // empty board
+    pub fn new(rows: usize, columns: usize) -> Self {
+        let mut this = Self {
+            rows,
+            columns,
+            mines: vec![false; rows * columns],
+            start: vec![false; rows * columns],
+            board: Game::from_mines(rows, columns, 1, &vec![Some(0); rows * columns]),
+            path: String::from("puzzle.txt"),
+            stuck: vec![false; rows * columns],
+            message: None,
+            testing: false,
+        };
+        this.update();
+        this
+    }
+
+    // the board to play, None without a free cell
+    pub fn game(&self) -> Option<Game> {
+        self.mines.contains(&false).then(|| self.board.clone())
+    }
+
+    // rebuild the board after an edit, the last check is out of date
+    fn update(&mut self) {
+        let cells: Vec<Option<u8>> = self.mines.iter().map(|&m| Some(u8::from(m))).collect();
+        self.board = Game::from_mines(self.rows, self.columns, 1, &cells);
+        let start: Vec<usize> = (0..self.start.len()).filter(|&i| self.start[i]).collect();
+        self.board.reveal_start(&start);
+        self.stuck.fill(false);
+        self.message = None;
+    }
+
+    // solve the puzzle from its start, it's unique if the solver needs no guess
+    fn check(&mut self) {
+        if !self.start.contains(&true) {
+            self.message = Some(Err(String::from("reveal some cells to set the start")));
+            return;
+        }
+
+        let solved = solver::solve(&self.board);
+        self.stuck = solved.map.iter()
+            .map(|cell| cell.mines == 0 && cell.flag != CellTag::SELECTED)
+            .collect();
+        let stuck = self.stuck.iter().filter(|&&s| s).count();
+        self.message = Some(if stuck == 0 {
+            Ok(String::from("uniquely solvable, no guess needed"))
+        } else {
+            Err(format!("{} free cells need a guess", stuck))
+        });
+    }
+
+    fn save(&mut self) {
+        let text = format!("; {} mines\n{}", self.board.n_mines, board_text(&self.board));
+        self.message = Some(match std::fs::write(self.path.trim(), text) {
+            Ok(()) => Ok(format!("saved to {}", self.path.trim())),
+            Err(e) => Err(format!("can't write {}: {}", self.path.trim(), e)),
+        });
+    }
+
+    // draw the board inside a scroll area, clicks edit it
+    // returns the cell size after zooming
+    pub fn ui(&mut self, ui: &mut egui::Ui, cell_size: f32, look: &Look) -> f32 {
+        let step = cell_size + 1.0;
+        let theme = &look.theme;
+
+        egui::ScrollArea::both().show_viewport(ui, |ui, viewport| {
+            let viewport = viewport.translate(ui.max_rect().min.to_vec2());
+            let (rect, response) = ui.allocate_exact_size(
+                egui::Vec2::new(self.columns as f32 * step, self.rows as f32 * step),
+                egui::Sense::click_and_drag());
+            let origin = rect.min;
+
+            let cell_at = |p: egui::Pos2| {
+                let v = p - origin;
+                let (r, c) = ((v.y / step) as usize, (v.x / step) as usize);
+                (v.x >= 0. && v.y >= 0. && r < self.rows && c < self.columns).then_some((r * self.columns) + c)
+            };
+            let pointer = response.hover_pos().and_then(cell_at);
+
+            //visible rows and columns
+            let first = |v: f32| (v.max(0.) / step) as usize;
+            let rows = first(viewport.min.y - origin.y)..=first(viewport.max.y - origin.y);
+            let columns = first(viewport.min.x - origin.x)..=first(viewport.max.x - origin.x);
+
+            for r in rows.take_while(|&r| r < self.rows) {
+                for c in columns.clone().take_while(|&c| c < self.columns) {
+                    let ind = (r * self.columns) + c;
+                    let cell = &self.board.map[ind];
+                    let lbl = match (cell.mines, cell.val) {
+                        (0, 0) => String::new(),
+                        (0, val) => val.to_string(),
+                        _ => look.glyphs.bomb.clone(),
+                    };
+                    let color = if cell.mines > 0 { theme.red } else { look.number(cell.val) };
+
+                    //the start is revealed, the cells left to guess are highlighted
+                    let fill = if self.stuck[ind] {
+                        theme.yellow.linear_multiply(0.4)
+                    } else if cell.flag == CellTag::SELECTED {
+                        theme.mantle
+                    } else if pointer == Some(ind) {
+                        theme.surface2
+                    } else {
+                        theme.surface0
+                    };
+
+                    let rect = egui::Rect::from_min_size(
+                        origin + egui::Vec2::new(c as f32 * step, r as f32 * step),
+                        egui::Vec2::new(cell_size, cell_size));
+                    paint_cell(ui, rect, &lbl, color, fill);
+                }
+            }
+
+            let size = zoom_and_pan(ui, &response, origin, cell_size);
+
+            if let Some(ind) = pointer {
+                if response.clicked_by(egui::PointerButton::Primary) {
+                    self.mines[ind] = !self.mines[ind];
+                    self.start[ind] = false;
+                    self.update();
+                } else if response.secondary_clicked() && !self.mines[ind] {
+                    self.start[ind] = !self.start[ind];
+                    self.update();
+                }
+            }
+
+            size
+        }).inner
+    }
+
+    // tools window
+    // returns (open, play), playing tests the board
+    pub fn window(&mut self, ctx: &egui::Context, look: &Look) -> (bool, bool) {
+        let mut open = true;
+        let mut play = false;
+        egui::Window::new("Board editor")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if self.testing {
+                    ui.label("Playtesting the board.");
+                    if ui.button("Back to editing").clicked() {
+                        self.testing = false;
+                    }
+                    return;
+                }
+
+                ui.label("Click to place or remove a mine,\nright click to reveal a cell at the start.");
+                ui.label(format!("{}x{}, {} mines, {} cells revealed at the start",
+                    self.rows, self.columns, self.board.n_mines, self.board.selected));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Check").clicked() {
+                        self.check();
+                    }
+                    if ui.add_enabled(self.mines.contains(&false), egui::Button::new("Playtest")).clicked() {
+                        play = true;
+                    }
+                    if ui.button("Clear start").clicked() {
+                        self.start.fill(false);
+                        self.update();
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.mines.fill(false);
+                        self.start.fill(false);
+                        self.update();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::Vec2::new(200., 24.),
+                        egui::TextEdit::singleline(&mut self.path));
+                    if ui.button("Save").clicked() {
+                        self.save();
+                    }
+                });
+
+                match &self.message {
+                    Some(Ok(m)) => { ui.colored_label(look.theme.green, m); },
+                    Some(Err(e)) => { ui.colored_label(look.theme.red, e); },
+                    None => {},
+                }
+            });
+
+        (open, play)
+    }
+}
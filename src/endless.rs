@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use rustymines::engine::CellTag;
+
+use crate::{paint_cell, zoom_and_pan};
+use crate::skin::{Skin, Tile};
+use crate::theme::Look;
+
+//chunk side in cells
+pub const CHUNK: i64 = 16;
+//cells from the origin the view can pan to, in each direction
+const EXTENT: i64 = 50_000;
+//a single flood reveal stops this far (in cells) from the clicked cell
+const FLOOD_RADIUS: i64 = 64;
+
+// a CHUNK x CHUNK block of the endless board
+struct Chunk {
+    mines: Vec<bool>, //mined cells
+    vals: Vec<u8>, //number of neighbour mines, set when the cell is selected
+    flags: Vec<CellTag>, //cell tags
+}
+
+//endless board: mines are generated per chunk, on demand,
+//from the seed and the density
+pub struct Endless {
+    pub seed: u64, //board seed
+    pub density: f64, //probability of a cell being mined
+    chunks: HashMap<(i64, i64), Chunk>, //generated chunks by (chunk row, chunk column)
+    pub cleared: usize, //total cells selected (the score)
+    pub flagged: usize, //total flagged cells
+    pub lost: bool, //a mine was selected
+    pub recorded: bool, //the lost run is in the results
+    centred: bool, //the view was scrolled to the origin
+}
+
+//chunk key and index inside the chunk for a board position
+fn locate(r: i64, c: i64) -> ((i64, i64), usize) {
+    let key = (r.div_euclid(CHUNK), c.div_euclid(CHUNK));
+    let ind = (r.rem_euclid(CHUNK) * CHUNK) + c.rem_euclid(CHUNK);
+    (key, ind as usize)
+}
+
+impl Endless {
+    // new board with the origin already opened
+    pub fn new(seed: u64, density: f64) -> Self {
+        let mut this = Self {
+            seed,
+            density,
+            chunks: HashMap::new(),
+            cleared: 0,
+            flagged: 0,
+            lost: false,
+            recorded: false,
+            centred: false,
+        };
+
+        this.reveal(0, 0);
+
+        this
+    }
+
+    // get the chunk with key, generating its mines if it doesn't exist yet
+    fn chunk(&mut self, key: (i64, i64)) -> &mut Chunk {
+        let (seed, density) = (self.seed, self.density);
+
+        self.chunks.entry(key).or_insert_with(|| {
+            //every chunk has its own generator derived from the board seed
+            let mut rng = ChaCha8Rng::seed_from_u64(seed
+                ^ (key.0 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (key.1 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+
+            let len = (CHUNK * CHUNK) as usize;
+            let mines = (0..len as i64)
+                .map(|i| {
+                    let r = (key.0 * CHUNK) + (i / CHUNK);
+                    let c = (key.1 * CHUNK) + (i % CHUNK);
+                    let mined = rng.gen_bool(density);
+                    //keep the cells around the origin free
+                    mined && (r.abs() > 1 || c.abs() > 1)
+                })
+                .collect();
+
+            Chunk {
+                mines,
+                vals: vec![0; len],
+                flags: vec![CellTag::UNSELECTED; len],
+            }
+        })
+    }
+
+    fn is_mine(&mut self, r: i64, c: i64) -> bool {
+        let (key, ind) = locate(r, c);
+        self.chunk(key).mines[ind]
+    }
+
+    // cell tag, cells in chunks not generated yet are UNSELECTED
+    pub fn flag(&self, r: i64, c: i64) -> CellTag {
+        let (key, ind) = locate(r, c);
+        self.chunks.get(&key).map_or(CellTag::UNSELECTED, |chunk| chunk.flags[ind])
+    }
+
+    // number of neighbour mines of a selected cell
+    pub fn val(&self, r: i64, c: i64) -> u8 {
+        let (key, ind) = locate(r, c);
+        self.chunks.get(&key).map_or(0, |chunk| chunk.vals[ind])
+    }
+
+    // set the cell to SELECTED, count its neighbour mines
+    // and return the count
+    fn set_selected(&mut self, r: i64, c: i64) -> u8 {
+        let mut count = 0;
+        for nr in (r - 1)..=(r + 1) {
+            for nc in (c - 1)..=(c + 1) {
+                if (nr, nc) != (r, c) && self.is_mine(nr, nc) {
+                    count += 1;
+                }
+            }
+        }
+
+        let (key, ind) = locate(r, c);
+        let chunk = self.chunk(key);
+        chunk.flags[ind] = CellTag::SELECTED;
+        chunk.vals[ind] = count;
+        self.cleared += 1;
+
+        count
+    }
+
+    // select the cell, if it's a zero cell clean all the neighbouring
+    // cells with 0 up to the first non zero cell, across chunks
+    pub fn reveal(&mut self, row: i64, column: i64) {
+        if self.is_mine(row, column) {
+            let (key, ind) = locate(row, column);
+            self.chunk(key).flags[ind] = CellTag::DEAD;
+            self.lost = true;
+            return;
+        }
+
+        let mut cells: Vec<(i64, i64)> = Vec::new();
+        if self.flag(row, column) == CellTag::UNSELECTED {
+            if self.set_selected(row, column) > 0 {
+                return;
+            }
+        } else if self.val(row, column) > 0 {
+            return;
+        }
+        //a selected zero cell can be clicked to continue a stopped flood
+        cells.push((row, column));
+
+        while let Some((r, c)) = cells.pop() {
+            if (r - row).abs() >= FLOOD_RADIUS
+                || (c - column).abs() >= FLOOD_RADIUS {
+                continue;
+            }
+
+            for nr in (r - 1)..=(r + 1) {
+                for nc in (c - 1)..=(c + 1) {
+                    if self.flag(nr, nc) == CellTag::UNSELECTED
+                        && !self.is_mine(nr, nc)
+                        && self.set_selected(nr, nc) == 0 {
+                        cells.push((nr, nc));
+                    }
+                }
+            }
+        }
+    }
+
+    // flag or unflag the cell
+    pub fn toggle_flag(&mut self, r: i64, c: i64) {
+        let (key, ind) = locate(r, c);
+        let chunk = self.chunk(key);
+
+        match chunk.flags[ind] {
+            CellTag::UNSELECTED => {
+                chunk.flags[ind] = CellTag::FLAGGED;
+                self.flagged += 1;
+            },
+            CellTag::FLAGGED => {
+                chunk.flags[ind] = CellTag::UNSELECTED;
+                self.flagged -= 1;
+            },
+            _ => {},
+        }
+    }
+
+    // draw the visible part of the board inside a scroll area
+    // only the cells of the visible chunks are painted
+    // returns the cell size after zooming
+    pub fn ui(&mut self, ui: &mut egui::Ui, cell_size: f32,
+        look: &Look, skin: Option<&Skin>, playing: bool) -> f32 {
+        let step = cell_size + 1.0;
+        let side = (2 * EXTENT) as f32 * step;
+
+        let mut area = egui::ScrollArea::both().auto_shrink(false);
+        if !self.centred {
+            //start with the origin in the middle of the view
+            let size = ui.available_size();
+            area = area.scroll_offset(egui::Vec2::new(
+                EXTENT as f32 * step - size.x / 2.,
+                EXTENT as f32 * step - size.y / 2.));
+            self.centred = true;
+        }
+
+        area.show_viewport(ui, |ui, viewport| {
+            let playing = playing && !self.lost;
+
+            let (rect, response) = ui.allocate_exact_size(
+                egui::Vec2::new(side, side), egui::Sense::click_and_drag());
+            let origin = rect.min;
+
+            //board position of a point relative to the board widget
+            let cell = |v: egui::Vec2| {
+                ((v.y / step).floor() as i64 - EXTENT, (v.x / step).floor() as i64 - EXTENT)
+            };
+            let pointer = response.hover_pos().map(|p| cell(p - origin));
+
+            //visible chunks
+            let (r0, c0) = cell(viewport.min.to_vec2());
+            let (r1, c1) = cell(viewport.max.to_vec2());
+
+            for kr in r0.div_euclid(CHUNK)..=r1.div_euclid(CHUNK) {
+                for kc in c0.div_euclid(CHUNK)..=c1.div_euclid(CHUNK) {
+                    for i in 0..(CHUNK * CHUNK) {
+                        let r = (kr * CHUNK) + (i / CHUNK);
+                        let c = (kc * CHUNK) + (i % CHUNK);
+                        let rect = egui::Rect::from_min_size(
+                            origin + egui::Vec2::new(
+                                (c + EXTENT) as f32 * step, (r + EXTENT) as f32 * step),
+                            egui::Vec2::new(cell_size, cell_size));
+
+                        let hovered = playing && pointer == Some((r, c));
+                        self.paint(ui, rect, (r, c), look, skin, hovered);
+                    }
+                }
+            }
+
+            let size = zoom_and_pan(ui, &response, origin, cell_size);
+
+            let Some((r, c)) = pointer.filter(|_| playing) else {
+                return size;
+            };
+
+            //selected zero cells can be clicked to continue a stopped flood
+            let tag = self.flag(r, c);
+            if response.clicked_by(egui::PointerButton::Primary) && (tag == CellTag::UNSELECTED
+                || (tag == CellTag::SELECTED && self.val(r, c) == 0)) {
+                self.reveal(r, c);
+            }
+
+            if response.secondary_clicked() {
+                self.toggle_flag(r, c);
+            }
+
+            size
+        }).inner
+    }
+
+    fn paint(&self, ui: &egui::Ui, rect: egui::Rect, (r, c): (i64, i64),
+        look: &Look, skin: Option<&Skin>, hovered: bool) {
+        let theme = &look.theme;
+
+        if let Some(skin) = skin {
+            let tile = match self.flag(r, c) {
+                CellTag::FLAGGED => Tile::Flag,
+                CellTag::DEAD => Tile::Exploded,
+                CellTag::SELECTED => Tile::Number(self.val(r, c)),
+                _ => Tile::Covered,
+            };
+            skin.paint(ui.painter(), rect, tile);
+            if hovered && tile == Tile::Covered {
+                ui.painter().rect_filled(rect, 0.0, theme.surface2.linear_multiply(0.5));
+            }
+            return;
+        }
+
+        let (lbl, color, fill) = match self.flag(r, c) {
+            CellTag::FLAGGED => (look.glyphs.flag.clone(), theme.red, theme.surface0),
+            CellTag::DEAD => (look.glyphs.dead.clone(), theme.red, theme.mantle),
+            CellTag::SELECTED => {
+                let val = self.val(r, c);
+                let lbl = if val > 0 { format!("{}", val) } else { String::new() };
+                (lbl, look.number(val), theme.mantle)
+            },
+            _ => (String::new(), theme.text,
+                if hovered { theme.surface2 } else { theme.surface0 }),
+        };
+
+        paint_cell(ui, rect, &lbl, color, fill);
+    }
+}
@@ -0,0 +1,936 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand::seq::SliceRandom;
+
+use crate::history::{Counters, History};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellTag {
+    UNSELECTED, //unselected cell
+    FLAGGED,    //flagged cell
+    SELECTED,   //selected cell
+    DEAD,       //we got killed in this cell
+    HOLE,       //not part of the board (shaped boards)
+}
+
+//board shapes available in the settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Rectangle,
+    Heart,
+    Ring,
+    Bitmap, //loaded from a pbm file
+}
+
+impl Shape {
+    pub const ALL: [Shape; 4] = [Shape::Rectangle, Shape::Heart, Shape::Ring, Shape::Bitmap];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Rectangle => "rectangle",
+            Shape::Heart => "heart",
+            Shape::Ring => "ring",
+            Shape::Bitmap => "bitmap",
+        }
+    }
+}
+
+//standard boards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Beginner, Preset::Intermediate, Preset::Expert];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Beginner => "beginner",
+            Preset::Intermediate => "intermediate",
+            Preset::Expert => "expert",
+        }
+    }
+
+    // (rows, columns, mines)
+    pub fn board(&self) -> (usize, usize, usize) {
+        match self {
+            Preset::Beginner => (9, 9, 10),
+            Preset::Intermediate => (16, 16, 40),
+            Preset::Expert => (16, 30, 99),
+        }
+    }
+}
+
+// cell data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub val: u8, // number of neighbour mines (total of their mines)
+    pub mines: u8, // number of mines in this cell (0..max_mines)
+    pub flags: u8, // flag counter when FLAGGED (1..max_mines)
+    pub flag: CellTag,
+}
+
+// game clock, adds up the intervals it runs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Clock {
+    elapsed: Duration, //time of the finished intervals
+    start: Option<Instant>, //start of the running interval
+}
+
+impl Clock {
+    // start or stop the clock
+    pub fn run(&mut self, running: bool) {
+        match (running, self.start) {
+            (true, None) => self.start = Some(Instant::now()),
+            (false, Some(start)) => {
+                self.elapsed += start.elapsed();
+                self.start = None;
+            },
+            _ => {},
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed + self.start.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+}
+
+//get a board mask: Vec<bool> with len = rows * columns
+//true for the positions that are part of the board, false for holes
+pub fn get_mask(shape: Shape, rows: usize, columns: usize) -> Vec<bool> {
+    let mut mask = vec![true; rows * columns];
+
+    for r in 0..rows {
+        for c in 0..columns {
+            //cell center scaled to -1.0..1.0
+            let x = ((c as f32 + 0.5) / columns as f32) * 2.0 - 1.0;
+            let y = ((r as f32 + 0.5) / rows as f32) * 2.0 - 1.0;
+
+            mask[(r * columns) + c] = match shape {
+                Shape::Heart => {
+                    //heart curve (x^2 + y^2 - 1)^3 - x^2 * y^3 <= 0
+                    //with y pointing up and a small margin around it
+                    let (x, y) = (x * 1.2, -y * 1.2 + 0.15);
+                    (x * x + y * y - 1.0).powi(3) - x * x * y.powi(3) <= 0.0
+                },
+                Shape::Ring => {
+                    let d = (x * x + y * y).sqrt();
+                    (0.45..=1.0).contains(&d)
+                },
+                _ => true,
+            };
+        }
+    }
+
+    mask
+}
+
+//load a board mask from a pbm (portable bitmap) file, P1 or P4
+//black pixels are part of the board, white pixels are holes
+//returns (rows, columns, mask)
+pub fn load_pbm(path: &str) -> Result<(usize, usize, Vec<bool>), String> {
+    let data = std::fs::read(path)
+        .map_err(|e| format!("can't read {}: {}", path, e))?;
+
+    //read the header tokens skipping whitespace and comments
+    let mut pos = 0;
+    let mut header: Vec<String> = Vec::new();
+    while header.len() < 3 && pos < data.len() {
+        if data[pos] == b'#' {
+            while pos < data.len() && data[pos] != b'\n' {
+                pos += 1;
+            }
+        } else if data[pos].is_ascii_whitespace() {
+            pos += 1;
+        } else {
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() && data[pos] != b'#' {
+                pos += 1;
+            }
+            header.push(String::from_utf8_lossy(&data[start..pos]).to_string());
+        }
+    }
+
+    if header.len() < 3 {
+        return Err(format!("{}: incomplete pbm header", path));
+    }
+
+    let columns: usize = header[1].parse()
+        .map_err(|_| format!("{}: bad width '{}'", path, header[1]))?;
+    let rows: usize = header[2].parse()
+        .map_err(|_| format!("{}: bad height '{}'", path, header[2]))?;
+    //the same board sizes as the settings, checked before allocating
+    if !(4..=500).contains(&rows) || !(4..=500).contains(&columns) {
+        return Err(format!("{}: {}x{} bitmap, width and height must be between 4 and 500",
+            path, columns, rows));
+    }
+
+    let mut mask: Vec<bool> = Vec::with_capacity(rows * columns);
+    match header[0].as_str() {
+        "P1" => {
+            //ascii bits, whitespace between them is optional
+            let mut comment = false;
+            for &b in &data[pos..] {
+                match b {
+                    b'#' => comment = true,
+                    b'\n' => comment = false,
+                    b'0' if !comment => mask.push(false),
+                    b'1' if !comment => mask.push(true),
+                    _ => {},
+                }
+                if mask.len() == rows * columns {
+                    break;
+                }
+            }
+        },
+        "P4" => {
+            //a single whitespace and then rows of packed bits
+            let bytes = &data[(pos + 1).min(data.len())..];
+            let stride = columns.div_ceil(8);
+            for r in 0..rows {
+                for c in 0..columns {
+                    match bytes.get((r * stride) + (c / 8)) {
+                        Some(b) => mask.push(b & (0x80 >> (c % 8)) != 0),
+                        None => break,
+                    }
+                }
+            }
+        },
+        m => return Err(format!("{}: unsupported format '{}', expected P1 or P4", path, m)),
+    }
+
+    if mask.len() < rows * columns {
+        return Err(format!("{}: missing pixel data", path));
+    }
+    //a mine and a free cell at least
+    if mask.iter().filter(|&&m| m).count() < 2 {
+        return Err(format!("{}: the bitmap has less than 2 black pixels", path));
+    }
+
+    Ok((rows, columns, mask))
+}
+
+//get the indexes of the neighbours of the cell with index ind
+//in a board of layers x rows x columns
+//8 neighbours in a single layer board, up to 26 with more layers
+pub fn neighbours(ind: usize, rows: usize, columns: usize, layers: usize) -> Vec<usize> {
+    let l = ind / (rows * columns);
+    let r = (ind / columns) % rows;
+    let c = ind % columns;
+
+    let mut n_vec: Vec<usize> = Vec::with_capacity(26);
+    for nl in l.saturating_sub(1)..=(l + 1).min(layers - 1) {
+        for nr in r.saturating_sub(1)..=(r + 1).min(rows - 1) {
+            for nc in c.saturating_sub(1)..=(c + 1).min(columns - 1) {
+                if (nl, nr, nc) != (l, r, c) {
+                    n_vec.push((((nl * rows) + nr) * columns) + nc);
+                }
+            }
+        }
+    }
+
+    n_vec
+}
+
+//get a new map: Vec<Cell> with len = layers * rows * columns
+//positions outside the mask are holes, they never get mines
+//each cell can hold up to max_mines mines
+pub fn get_map_vec(rows: usize, columns: usize, layers: usize,
+    n_mines: usize, max_mines: u8, mask: &[bool], rng: &mut impl Rng) -> Vec<Cell> {
+    let len = layers * rows * columns;
+    //set the cells value to 0 and flagged with UNSELECTED or HOLE
+    let mut m_vec: Vec<Cell> = (0..len)
+        .map(|i| Cell {
+            val: 0,
+            mines: 0,
+            flags: 0,
+            flag: if mask[i] { CellTag::UNSELECTED } else { CellTag::HOLE },
+        })
+        .collect();
+
+    //shuffle the board positions, max_mines slots for each one
+    let mut slots: Vec<usize> = (0..len)
+        .filter(|&i| mask[i])
+        .flat_map(|i| std::iter::repeat_n(i, max_mines.into()))
+        .collect();
+    slots.shuffle(rng);
+
+    //set a mine in each of the first n_mines shuffled slots
+    for &i in slots.iter().take(n_mines) {
+        m_vec[i].mines += 1;
+    }
+
+    count_neighbours(&mut m_vec, rows, columns, layers);
+
+    m_vec
+}
+
+// check the mines fit a rectangular board, leaving at least a free cell
+pub fn check_mines(n_mines: usize, rows: usize, columns: usize, layers: usize, max_mines: u8) -> Result<(), String> {
+    let fit = (rows * columns * layers).saturating_sub(1) * usize::from(max_mines);
+    if n_mines == 0 {
+        return Err(String::from("mines must be at least 1"));
+    }
+    if n_mines > fit {
+        let layers = if layers > 1 { format!("x{}", layers) } else { String::new() };
+        return Err(format!("{} mines don't fit a {}x{}{} board, use at most {}",
+            n_mines, rows, columns, layers, fit));
+    }
+    Ok(())
+}
+
+// check the mines fit the open cells of a mask on every layer,
+// leaving at least a free cell
+pub fn check_mask_mines(n_mines: usize, mask: &[bool], layers: usize, max_mines: u8) -> Result<(), String> {
+    let cells = mask.iter().filter(|&&m| m).count() * layers;
+    let fit = cells.saturating_sub(1) * usize::from(max_mines);
+    if n_mines == 0 {
+        return Err(String::from("mines must be at least 1"));
+    }
+    if n_mines > fit {
+        return Err(format!("{} mines don't fit the {} cells of the board, use at most {}",
+            n_mines, cells, fit));
+    }
+    Ok(())
+}
+
+// seed from a text, a number or any other text hashed, None if empty
+pub fn seed_from(text: &str) -> Option<u64> {
+    match text.trim() {
+        "" => None,
+        s => Some(s.parse().unwrap_or_else(|_| {
+            s.bytes().fold(0u64, |h, b| h.wrapping_mul(31).wrapping_add(u64::from(b)))
+        })),
+    }
+}
+
+//board of the daily challenge, the same for everyone on a day
+pub const DAILY: Preset = Preset::Intermediate;
+
+// utc date of a time as yyyy-mm-dd
+pub fn utc_date(time: SystemTime) -> String {
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+
+    //days since 1970-01-01 to a civil date, years start in march
+    let z = secs.div_euclid(86_400) + 719_468;
+    let (era, doe) = (z.div_euclid(146_097), z.rem_euclid(146_097));
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// game of the daily challenge of a date
+pub fn daily_game(date: &str) -> Game {
+    let (rows, columns, n_mines) = DAILY.board();
+    let seed = seed_from(&format!("daily {}", date)).unwrap_or_default();
+    Game::new(rows, columns, 1, n_mines, 1, &get_mask(Shape::Rectangle, rows, columns), seed)
+}
+
+// for each cell count the neighbouring mines
+fn count_neighbours(map: &mut [Cell], rows: usize, columns: usize, layers: usize) {
+    for ind in 0..map.len() {
+
+        //this cell is mined jump to the next one
+        if map[ind].mines > 0 {
+            continue;
+        }
+
+        map[ind].val = neighbours(ind, rows, columns, layers)
+            .iter()
+            .map(|&i| map[i].mines)
+            .sum();
+    }
+}
+
+//parse a board file: layers of rows separated by blank lines,
+//'.' a free cell, 'o' a free cell revealed at the start, '*' a mine,
+//'2' to '9' a cell with that many mines and '-' a hole,
+//lines starting with ';' are comments
+pub fn parse_board(text: &str) -> Result<Game, String> {
+    let mut layers: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in text.lines().map(str::trim_end).filter(|l| !l.starts_with(';')) {
+        match layers.last_mut() {
+            Some(layer) if !line.is_empty() => layer.push(line),
+            Some(layer) if !layer.is_empty() => layers.push(Vec::new()),
+            _ => {},
+        }
+    }
+    layers.retain(|layer| !layer.is_empty());
+
+    let rows = layers.first().ok_or("the board is empty")?.len();
+    let columns = layers[0][0].chars().count();
+
+    let mut cells = Vec::with_capacity(layers.len() * rows * columns);
+    let mut start = Vec::new();
+    for (l, layer) in layers.iter().enumerate() {
+        if layer.len() != rows {
+            return Err(format!("layer {} has {} rows, the first one has {}", l + 1, layer.len(), rows));
+        }
+
+        for (r, line) in layer.iter().enumerate() {
+            if line.chars().count() != columns {
+                return Err(format!("layer {} row {} has {} columns, expected {}",
+                    l + 1, r + 1, line.chars().count(), columns));
+            }
+
+            for (c, ch) in line.chars().enumerate() {
+                if ch == 'o' {
+                    start.push(cells.len());
+                }
+                cells.push(match ch {
+                    '.' | 'o' => Some(0),
+                    '*' => Some(1),
+                    '2'..='9' => Some(ch as u8 - b'0'),
+                    '-' => None,
+                    _ => return Err(format!("layer {} row {} column {}: unknown cell '{}'",
+                        l + 1, r + 1, c + 1, ch)),
+                });
+            }
+        }
+    }
+
+    if !cells.contains(&Some(0)) {
+        return Err(String::from("the board has no free cell"));
+    }
+
+    let mut game = Game::from_mines(rows, columns, layers.len(), &cells);
+    game.reveal_start(&start);
+    Ok(game)
+}
+
+// board file of a game, the revealed free cells are the start
+pub fn board_text(game: &Game) -> String {
+    let mut text = String::new();
+    for (ind, cell) in game.map.iter().enumerate() {
+        let (l, r, c) = game.position(ind);
+        if c == 0 && ind > 0 {
+            text.push('\n');
+            if r == 0 && l > 0 {
+                text.push('\n');
+            }
+        }
+
+        text.push(match (cell.flag, cell.mines) {
+            (CellTag::HOLE, _) => '-',
+            (CellTag::SELECTED, 0) => 'o',
+            (_, 0) => '.',
+            (_, 1) => '*',
+            (_, n) => char::from(b'0' + n.min(9)),
+        });
+    }
+    text.push('\n');
+    text
+}
+
+//a move on the board, as sent to the other players of a race
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Move {
+    Reveal(usize),
+    Flag(usize, bool), //cell and wrap, see add_flag
+    Unflag(usize),
+    Chord(usize),
+}
+
+impl Move {
+    pub fn text(&self) -> String {
+        match self {
+            Move::Reveal(ind) => format!("reveal {}", ind),
+            Move::Flag(ind, wrap) => format!("flag {} {}", ind, wrap),
+            Move::Unflag(ind) => format!("unflag {}", ind),
+            Move::Chord(ind) => format!("chord {}", ind),
+        }
+    }
+
+    // index of the cell played
+    pub fn cell(&self) -> usize {
+        match *self {
+            Move::Reveal(ind) | Move::Flag(ind, _) | Move::Unflag(ind) | Move::Chord(ind) => ind,
+        }
+    }
+
+    // move from its text, None if it isn't one
+    pub fn parse(text: &str) -> Option<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words[..] {
+            ["reveal", ind] => Some(Move::Reveal(ind.parse().ok()?)),
+            ["flag", ind, wrap] => Some(Move::Flag(ind.parse().ok()?, wrap.parse().ok()?)),
+            ["unflag", ind] => Some(Move::Unflag(ind.parse().ok()?)),
+            ["chord", ind] => Some(Move::Chord(ind.parse().ok()?)),
+            _ => None,
+        }
+    }
+}
+
+//what happened in a game, for the listeners to drain from its events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Start, //a new board
+    Reveal(usize), //a covered cell was revealed by the player
+    Opening(usize), //a zero cell opened this many cells around it
+    Flag(usize),
+    Unflag(usize),
+    Chord(usize), //a chord that revealed cells
+    Cleared(bool), //the last free cell was revealed, true with undone moves
+    Win(bool), //true with undone moves
+    Loss(bool), //true in practice games, the loss can be undone
+    Undo, //a move was taken back
+}
+
+//a game on a board of layers x rows x columns cells
+#[derive(Clone)]
+pub struct Game {
+    pub rows: usize, //map rows
+    pub columns: usize, //map columns
+    pub layers: usize, //map layers (3D boards)
+    pub n_mines: usize, //total number of mines
+    pub max_mines: u8, //maximum number of mines in a cell
+    pub cells: usize, //total board cells (holes excluded)
+    pub selected: usize, //total cells selected
+    pub f_mines: usize, //flagged mines
+    pub state: u8, //0-playing 1-winner 2-looser
+    pub practice: bool, //practice game, moves can be undone
+    pub seed: Option<u64>, //seed the mines were placed with, None for boards from a file
+    pub history: History, //moves of the practice game
+    pub events: Vec<Event>, //events of the moves not drained yet
+    pub map: Vec<Cell>, //game map
+}
+
+impl Game {
+    // new game, mask is the shape of a layer (rows * columns)
+    // n_mines is lowered to leave at least a free cell
+    // the same seed places the same mines, on every platform:
+    // ChaCha8 is portable where StdRng isn't
+    pub fn new(rows: usize, columns: usize, layers: usize,
+        n_mines: usize, max_mines: u8, mask: &[bool], seed: u64) -> Self {
+        //every layer has the same shape
+        let mask = mask.repeat(layers);
+
+        //make sure m_mines leaves at least a free cell
+        let cells = mask.iter().filter(|&&m| m).count();
+        let n_mines = n_mines.min(cells.saturating_sub(1) * usize::from(max_mines));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        Self {
+            rows,
+            columns,
+            layers,
+            n_mines,
+            max_mines,
+            cells,
+            selected: 0,
+            f_mines: 0,
+            state: 0,
+            practice: false,
+            seed: Some(seed),
+            history: History::default(),
+            events: vec![Event::Start],
+            map: get_map_vec(rows, columns, layers, n_mines, max_mines, &mask, &mut rng),
+        }
+    }
+
+    // game with the mines in place, cells has the mines of every
+    // cell (layers * rows * columns) with None for the holes
+    pub fn from_mines(rows: usize, columns: usize, layers: usize, cells: &[Option<u8>]) -> Self {
+        let mut map: Vec<Cell> = cells.iter()
+            .map(|&mines| Cell {
+                val: 0,
+                mines: mines.unwrap_or(0),
+                flags: 0,
+                flag: if mines.is_some() { CellTag::UNSELECTED } else { CellTag::HOLE },
+            })
+            .collect();
+        count_neighbours(&mut map, rows, columns, layers);
+
+        Self {
+            rows,
+            columns,
+            layers,
+            n_mines: map.iter().map(|c| usize::from(c.mines)).sum(),
+            max_mines: map.iter().map(|c| c.mines).max().unwrap_or(0).max(1),
+            cells: cells.iter().filter(|c| c.is_some()).count(),
+            selected: 0,
+            f_mines: 0,
+            state: 0,
+            practice: false,
+            seed: None,
+            history: History::default(),
+            events: vec![Event::Start],
+            map,
+        }
+    }
+
+    // 3BV, the least clicks that clear the board:
+    // one for each opening and one for each number outside them
+    pub fn bbbv(&self) -> usize {
+        let free = |cell: &Cell| cell.mines == 0 && cell.flag != CellTag::HOLE;
+        let mut seen = vec![false; self.map.len()];
+        let mut clicks = 0;
+
+        for ind in 0..self.map.len() {
+            if seen[ind] || !free(&self.map[ind]) || self.map[ind].val > 0 {
+                continue;
+            }
+
+            //the opening and the numbers around it go with one click
+            clicks += 1;
+            seen[ind] = true;
+            let mut cells = vec![ind];
+            while let Some(i) = cells.pop() {
+                for n in self.neighbours(i) {
+                    if !seen[n] && free(&self.map[n]) {
+                        seen[n] = true;
+                        if self.map[n].val == 0 {
+                            cells.push(n);
+                        }
+                    }
+                }
+            }
+        }
+
+        clicks + (0..self.map.len()).filter(|&i| !seen[i] && free(&self.map[i])).count()
+    }
+
+    // reveal the free cells of a starting position,
+    // they aren't moves of the game
+    pub fn reveal_start(&mut self, start: &[usize]) {
+        for &ind in start {
+            if self.map[ind].mines == 0 && self.map[ind].flag == CellTag::UNSELECTED {
+                self.reveal(ind);
+            }
+        }
+        self.events.retain(|&event| event == Event::Start);
+    }
+
+    // game from a board file
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("can't read {}: {}", path, e))?;
+        parse_board(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // (layer, row, column) of the cell with index ind
+    pub fn position(&self, ind: usize) -> (usize, usize, usize) {
+        (ind / (self.rows * self.columns), (ind / self.columns) % self.rows, ind % self.columns)
+    }
+
+    // index of the cell at (layer, row, column)
+    pub fn index(&self, l: usize, r: usize, c: usize) -> usize {
+        (((l * self.rows) + r) * self.columns) + c
+    }
+
+    pub fn neighbours(&self, ind: usize) -> Vec<usize> {
+        neighbours(ind, self.rows, self.columns, self.layers)
+    }
+
+    // set the map cell with index ind to SELECTED
+    // and increment the selected value
+    fn set_selected(&mut self, ind: usize) {
+        self.map[ind].flag = CellTag::SELECTED;
+        self.selected += 1;
+    }
+
+    //if a 0 cell is selected clean all neighbouring cells with 0
+    //up to the first non zero cell
+    fn clean_neighbour_cells(&mut self, ind: usize) {
+
+        // create a vector to push zero value cells to check neighbours
+        let mut cells: Vec<usize> = Vec::new();
+        cells.push(ind);
+
+        // pop the cells until the vector is empty
+        while let Some(ind) = cells.pop() {
+
+            //if it's not a zero value cell set it to selected
+            //and jump to the next one in the vector
+            if self.map[ind].val > 0 {
+                if self.map[ind].flag == CellTag::UNSELECTED {
+                    self.set_selected(ind);
+                }
+                continue;
+            }
+
+            //if the neighbour cell it's not a mine flag it selected
+            //and push it to the vector to have it's neighbours checked
+            for i in self.neighbours(ind) {
+                if self.map[i].mines == 0 && self.map[i].flag == CellTag::UNSELECTED {
+                    self.set_selected(i);
+                    cells.push(i);
+                }
+            }
+        }
+    }
+
+    // add a flag to the cell with index ind
+    // if wrap is set a cell that can't take more flags goes back to UNSELECTED
+    pub fn add_flag(&mut self, ind: usize, wrap: bool) {
+        let cell = &mut self.map[ind];
+
+        let full = cell.flags >= self.max_mines || self.f_mines >= self.n_mines;
+        if cell.flag == CellTag::FLAGGED && full {
+            if wrap {
+                self.f_mines -= usize::from(cell.flags);
+                cell.flags = 0;
+                cell.flag = CellTag::UNSELECTED;
+                self.events.push(Event::Unflag(ind));
+            }
+        } else if (cell.flag == CellTag::UNSELECTED || cell.flag == CellTag::FLAGGED)
+                && self.f_mines < self.n_mines {
+            cell.flag = CellTag::FLAGGED;
+            cell.flags += 1;
+            self.f_mines += 1;
+            self.events.push(Event::Flag(ind));
+        }
+    }
+
+    // remove a flag from the cell with index ind
+    pub fn remove_flag(&mut self, ind: usize) {
+        let cell = &mut self.map[ind];
+
+        if cell.flag == CellTag::FLAGGED {
+            cell.flags -= 1;
+            self.f_mines -= 1;
+            if cell.flags == 0 {
+                cell.flag = CellTag::UNSELECTED;
+            }
+            self.events.push(Event::Unflag(ind));
+        }
+    }
+
+    // all free cells selected and all mined cells
+    // flagged with their number of mines
+    pub fn is_winner(&self) -> bool {
+        self.map.iter().all(|cell| match cell.flag {
+            CellTag::UNSELECTED => false,
+            CellTag::FLAGGED => cell.flags == cell.mines,
+            _ => true,
+        })
+    }
+
+    // the game is won after the last flag
+    pub fn check_winner(&mut self) {
+        if self.state == 0 && self.is_winner() {
+            self.state = 1; //winner
+            self.events.push(Event::Win(self.history.used));
+        }
+    }
+
+    // select the cell with index ind
+    // and check if we are a looser or a winner
+    pub fn reveal(&mut self, ind: usize) {
+        if self.map[ind].flag == CellTag::FLAGGED {
+            self.f_mines -= usize::from(self.map[ind].flags);
+            self.map[ind].flags = 0;
+        }
+
+        self.set_selected(ind);
+        self.events.push(Event::Reveal(ind));
+
+        if self.map[ind].mines > 0 {
+            self.state = 2; //looser
+            self.map[ind].flag = CellTag::DEAD;
+            self.check_looser_map();
+            self.events.push(Event::Loss(self.practice));
+        } else {
+            if self.map[ind].val == 0 {
+                let selected = self.selected;
+                self.clean_neighbour_cells(ind);
+                self.events.push(Event::Opening(self.selected - selected));
+            }
+
+            let covered = self.map.iter()
+                .any(|cell| cell.mines == 0 && matches!(cell.flag, CellTag::UNSELECTED | CellTag::FLAGGED));
+            if !covered {
+                self.events.push(Event::Cleared(self.history.used));
+            }
+            self.check_winner();
+        }
+    }
+
+    // reveal the unflagged neighbours of a selected cell
+    // when the flags around it match its number
+    pub fn chord(&mut self, ind: usize) {
+        if self.map[ind].flag != CellTag::SELECTED || self.map[ind].val == 0 {
+            return;
+        }
+
+        let n_vec = self.neighbours(ind);
+        let flags: u8 = n_vec.iter()
+            .filter(|&&i| self.map[i].flag == CellTag::FLAGGED)
+            .map(|&i| self.map[i].flags)
+            .sum();
+        if flags != self.map[ind].val {
+            return;
+        }
+
+        if n_vec.iter().any(|&i| self.map[i].flag == CellTag::UNSELECTED) {
+            self.events.push(Event::Chord(ind));
+        }
+        for i in n_vec {
+            if self.state == 0 && self.map[i].flag == CellTag::UNSELECTED {
+                self.reveal(i);
+            }
+        }
+    }
+
+    fn check_looser_map(&mut self) {
+        //set all unselected cells to selected
+        for cell in self.map.iter_mut() {
+            if cell.flag == CellTag::UNSELECTED {
+                cell.flag = CellTag::SELECTED;
+            }
+        }
+    }
+
+    fn counters(&self) -> Counters {
+        Counters { selected: self.selected, f_mines: self.f_mines, state: self.state }
+    }
+
+    fn set_counters(&mut self, counters: Counters) {
+        self.selected = counters.selected;
+        self.f_mines = counters.f_mines;
+        self.state = counters.state;
+    }
+
+    // do a move, flags can win the game
+    pub fn apply(&mut self, mv: Move) {
+        match mv {
+            Move::Reveal(ind) => self.reveal(ind),
+            Move::Flag(ind, wrap) => {
+                self.add_flag(ind, wrap);
+                self.check_winner();
+            },
+            Move::Unflag(ind) => {
+                self.remove_flag(ind);
+                self.check_winner();
+            },
+            Move::Chord(ind) => self.chord(ind),
+        }
+    }
+
+    // do a move, keeping its changes in the history of practice games
+    pub fn play(&mut self, f: impl FnOnce(&mut Self)) {
+        if !self.practice {
+            f(self);
+            return;
+        }
+
+        let (map, counters) = (self.map.clone(), self.counters());
+        f(self);
+        self.history.record(&map, counters, &self.map, self.counters());
+    }
+
+    // take back the last move, even a fatal one
+    pub fn undo(&mut self) {
+        if let Some(counters) = self.history.undo(&mut self.map) {
+            self.set_counters(counters);
+            self.events.push(Event::Undo);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(counters) = self.history.redo(&mut self.map) {
+            let state = self.state;
+            self.set_counters(counters);
+            if state == 0 && self.state == 2 {
+                self.events.push(Event::Loss(true));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // load a pbm written to a temporary file
+    fn pbm(name: &str, text: &str) -> Result<(usize, usize, Vec<bool>), String> {
+        let path = std::env::temp_dir().join(format!("rustymines-{}-{}.pbm", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let result = load_pbm(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn pbm_sizes_are_checked_before_allocating() {
+        assert!(pbm("huge", "P1 3000000000 3000000000\n").is_err());
+        assert!(pbm("small", "P1 3 4\n").is_err());
+        assert!(pbm("wide", "P1 501 4\n").is_err());
+    }
+
+    #[test]
+    fn pbm_needs_two_open_cells() {
+        assert!(pbm("closed", "P1 4 4\n1000 0000 0000 0000\n").is_err());
+        let (rows, columns, mask) = pbm("open", "P1 4 4\n1100 0000 0000 0000\n").unwrap();
+        assert_eq!((rows, columns), (4, 4));
+        assert_eq!(mask.iter().filter(|&&m| m).count(), 2);
+    }
+
+    #[test]
+    fn seeded_boards_are_portable() {
+        //a change here changes the daily, race and co-op boards
+        let game = daily_game("2026-10-19");
+        let mines: Vec<usize> = (0..game.map.len()).filter(|&i| game.map[i].mines > 0).take(8).collect();
+        assert_eq!(mines, [5, 6, 15, 19, 24, 41, 42, 56]);
+    }
+
+    #[test]
+    fn history_round_trip() {
+        for seed in 0..300 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let (rows, columns) = (rng.gen_range(4..=10), rng.gen_range(4..=10));
+            let layers = rng.gen_range(1..=2);
+            let mines = rng.gen_range(1..=rows * columns * layers / 4);
+            let mask = get_mask(Shape::Rectangle, rows, columns);
+            let mut game = Game::new(rows, columns, layers, mines, rng.gen_range(1..=2), &mask, seed);
+            game.practice = true;
+
+            //the board after every move that changed it
+            let mut boards = vec![(game.map.clone(), game.counters())];
+            let mut positions = vec![game.history.position()];
+            while game.state == 0 && boards.len() < 60 {
+                let ind = rng.gen_range(0..game.map.len());
+                let mv = match rng.gen_range(0..4) {
+                    0 => Move::Reveal(ind),
+                    1 => Move::Flag(ind, rng.gen()),
+                    2 => Move::Unflag(ind),
+                    _ => Move::Chord(ind),
+                };
+                if matches!(mv, Move::Reveal(_)) && game.map[ind].flag != CellTag::UNSELECTED {
+                    continue;
+                }
+                game.play(|game| game.apply(mv));
+                if game.history.position() != *positions.last().unwrap() {
+                    boards.push((game.map.clone(), game.counters()));
+                    positions.push(game.history.position());
+                }
+            }
+
+            for n in (0..boards.len() - 1).rev() {
+                game.undo();
+                assert!(game.map == boards[n].0 && game.counters() == boards[n].1, "seed {} undo {}", seed, n);
+                assert_eq!(game.history.position(), positions[n]);
+            }
+            assert!(!game.history.can_undo());
+            for n in 1..boards.len() {
+                game.redo();
+                assert!(game.map == boards[n].0 && game.counters() == boards[n].1, "seed {} redo {}", seed, n);
+                assert_eq!(game.history.position(), positions[n]);
+            }
+            assert!(!game.history.can_redo());
+        }
+    }
+}
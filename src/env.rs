@@ -0,0 +1,332 @@
+//reinforcement learning environment, gym style
+//
+//the actions are the cells to reveal, 0 to rows * columns - 1, followed when
+//flags are on by the cells to toggle a flag on, rows * columns to 2 * rows * columns - 1
+//the observation is CHANNELS one-hot planes of rows x columns, channel first:
+//covered, flagged, then revealed with 0 to 8 neighbour mines
+//an episode is won when all the free cells are revealed
+use crate::engine::{get_mask, CellTag, Game, Move, Shape};
+
+//planes of the observation
+pub const CHANNELS: usize = 11;
+
+//rewards of a step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    pub win: f32,
+    pub loss: f32,
+    pub progress: f32, //a reveal that opens cells
+    pub guess: f32, //a reveal with no revealed neighbour, added to progress
+    pub flag: f32, //a flag toggle
+    pub invalid: f32, //an action that doesn't change the board
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self { win: 1., loss: -1., progress: 0.1, guess: -0.05, flag: 0., invalid: -0.1 }
+    }
+}
+
+//board and rewards of an environment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub rows: usize,
+    pub columns: usize,
+    pub n_mines: usize,
+    pub flags: bool, //flag toggles are actions too
+    pub rewards: Rewards,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { rows: 9, columns: 9, n_mines: 10, flags: false, rewards: Rewards::default() }
+    }
+}
+
+//a game played by an agent
+pub struct Env {
+    pub config: Config,
+    pub game: Game,
+    pub done: bool,
+}
+
+// board of an episode
+fn board(config: &Config, seed: u64) -> Game {
+    let Config { rows, columns, n_mines, .. } = *config;
+    Game::new(rows, columns, 1, n_mines, 1, &get_mask(Shape::Rectangle, rows, columns), seed)
+}
+
+impl Env {
+    pub fn new(config: Config) -> Self {
+        Self { game: board(&config, 0), config, done: false }
+    }
+
+    // number of actions
+    pub fn actions(&self) -> usize {
+        let cells = self.config.rows * self.config.columns;
+        if self.config.flags { 2 * cells } else { cells }
+    }
+
+    // (channels, rows, columns) of the observation
+    pub fn shape(&self) -> [usize; 3] {
+        [CHANNELS, self.config.rows, self.config.columns]
+    }
+
+    // start a new episode, the same seed gives the same board
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.game = board(&self.config, seed);
+        self.done = false;
+        self.observation()
+    }
+
+    // all the free cells are revealed
+    pub fn won(&self) -> bool {
+        self.game.selected + self.game.n_mines == self.game.cells
+    }
+
+    // play an action, returns (observation, reward, done)
+    // an episode that is done stays done until reset
+    pub fn step(&mut self, action: usize) -> (Vec<f32>, f32, bool) {
+        let reward = self.act(action);
+        (self.observation(), reward, self.done)
+    }
+
+    // play an action without building the observation
+    pub fn act(&mut self, action: usize) -> f32 {
+        let rewards = self.config.rewards;
+        let cells = self.config.rows * self.config.columns;
+        if self.done || action >= self.actions() {
+            return rewards.invalid;
+        }
+
+        let ind = action % cells;
+        let flag = self.game.map[ind].flag;
+        if action >= cells {
+            if flag != CellTag::UNSELECTED && flag != CellTag::FLAGGED {
+                return rewards.invalid;
+            }
+            let mv = if flag == CellTag::FLAGGED { Move::Unflag(ind) } else { Move::Flag(ind, true) };
+            self.game.apply(mv);
+            //a flag is refused when they are all used
+            return if self.game.map[ind].flag == flag { rewards.invalid } else { rewards.flag };
+        }
+
+        if flag != CellTag::UNSELECTED {
+            return rewards.invalid;
+        }
+
+        //a reveal away from the revealed cells is a guess
+        let guess = self.game.selected > 0 && self.game.neighbours(ind).iter()
+            .all(|&i| self.game.map[i].flag != CellTag::SELECTED);
+        self.game.apply(Move::Reveal(ind));
+
+        if self.game.state == 2 {
+            self.done = true;
+            rewards.loss
+        } else if self.won() {
+            self.done = true;
+            rewards.win
+        } else if guess {
+            rewards.progress + rewards.guess
+        } else {
+            rewards.progress
+        }
+    }
+
+    // the actions that change the board
+    pub fn valid_actions(&self) -> Vec<bool> {
+        let covered = self.game.map.iter()
+            .map(|cell| !self.done && cell.flag == CellTag::UNSELECTED);
+        if !self.config.flags {
+            return covered.collect();
+        }
+
+        let flags_left = self.game.f_mines < self.game.n_mines;
+        let flaggable = self.game.map.iter()
+            .map(|cell| !self.done && match cell.flag {
+                CellTag::UNSELECTED => flags_left,
+                CellTag::FLAGGED => true,
+                _ => false,
+            });
+        covered.chain(flaggable).collect()
+    }
+
+    pub fn observation(&self) -> Vec<f32> {
+        let mut observation = vec![0.; CHANNELS * self.game.map.len()];
+        self.observe_into(&mut observation);
+        observation
+    }
+
+    // write the observation into a slice of CHANNELS * rows * columns
+    pub fn observe_into(&self, observation: &mut [f32]) {
+        let cells = self.game.map.len();
+        observation.fill(0.);
+        for (ind, cell) in self.game.map.iter().enumerate() {
+            let channel = match cell.flag {
+                CellTag::UNSELECTED => 0,
+                CellTag::FLAGGED => 1,
+                //the mines seen at the end are covered cells
+                CellTag::SELECTED if cell.mines == 0 => 2 + usize::from(cell.val.min(8)),
+                CellTag::SELECTED | CellTag::DEAD => 0,
+                CellTag::HOLE => continue,
+            };
+            observation[channel * cells + ind] = 1.;
+        }
+    }
+}
+
+//a batch of environments stepped together, the episodes that end
+//are reset with the next seed and give the first observation of the new one
+pub struct VecEnv {
+    pub envs: Vec<Env>,
+    next_seed: u64,
+}
+
+impl VecEnv {
+    pub fn new(config: Config, n: usize, seed: u64) -> Self {
+        let envs = (0..n).map(|_| Env::new(config)).collect();
+        Self { envs, next_seed: seed }
+    }
+
+    // reset the environments that are done with the next seeds
+    fn reset_done(&mut self) {
+        for env in self.envs.iter_mut().filter(|env| env.done) {
+            env.reset(self.next_seed);
+            self.next_seed = self.next_seed.wrapping_add(1);
+        }
+    }
+
+    // reset every environment, the observations one after the other
+    pub fn reset(&mut self) -> Vec<f32> {
+        for env in self.envs.iter_mut() {
+            env.done = true;
+        }
+        self.reset_done();
+        self.observations()
+    }
+
+    // play an action in each environment, returns (observations, rewards, dones)
+    pub fn step(&mut self, actions: &[usize]) -> (Vec<f32>, Vec<f32>, Vec<bool>) {
+        assert_eq!(actions.len(), self.envs.len(), "an action per environment");
+
+        //big batches are split between threads
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk = self.envs.len().div_ceil(threads).max(64);
+        let mut rewards = vec![0.; self.envs.len()];
+        if self.envs.len() <= chunk {
+            for ((env, &action), reward) in self.envs.iter_mut().zip(actions).zip(&mut rewards) {
+                *reward = env.act(action);
+            }
+        } else {
+            std::thread::scope(|scope| {
+                for ((envs, actions), rewards) in self.envs.chunks_mut(chunk)
+                    .zip(actions.chunks(chunk))
+                    .zip(rewards.chunks_mut(chunk)) {
+                    scope.spawn(move || {
+                        for ((env, &action), reward) in envs.iter_mut().zip(actions).zip(rewards) {
+                            *reward = env.act(action);
+                        }
+                    });
+                }
+            });
+        }
+
+        let dones: Vec<bool> = self.envs.iter().map(|env| env.done).collect();
+        self.reset_done();
+        (self.observations(), rewards, dones)
+    }
+
+    pub fn observations(&self) -> Vec<f32> {
+        let size = self.envs.first().map_or(0, |env| CHANNELS * env.game.map.len());
+        let mut observations = vec![0.; size * self.envs.len()];
+        for (env, observation) in self.envs.iter().zip(observations.chunks_mut(size.max(1))) {
+            env.observe_into(observation);
+        }
+        observations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(flags: bool) -> Env {
+        let mut env = Env::new(Config { rows: 4, columns: 4, n_mines: 1, flags, ..Config::default() });
+        env.reset(7);
+        env
+    }
+
+    fn cell(env: &Env, mined: bool) -> usize {
+        env.game.map.iter().position(|cell| (cell.mines > 0) == mined).unwrap()
+    }
+
+    #[test]
+    fn reset() {
+        let mut env = env(false);
+        let observation = env.reset(3);
+        assert_eq!(observation.len(), env.shape().iter().product::<usize>());
+        assert!(observation[..16].iter().all(|&v| v == 1.));
+        assert!(observation[16..].iter().all(|&v| v == 0.));
+
+        let mine = cell(&env, true);
+        env.reset(4);
+        env.reset(3);
+        assert_eq!(cell(&env, true), mine);
+        assert!(!env.done);
+    }
+
+    #[test]
+    fn step() {
+        let mut env = env(false);
+        let rewards = env.config.rewards;
+        let free = env.game.map.iter().position(|cell| cell.mines == 0 && cell.val > 0).unwrap();
+        let (observation, reward, done) = env.step(free);
+        assert_eq!((reward, done), (rewards.progress, false));
+        let val = usize::from(env.game.map[free].val);
+        assert_eq!(observation[(2 + val) * 16 + free], 1.);
+
+        assert_eq!(env.step(free).1, rewards.invalid);
+        assert_eq!(env.step(16).1, rewards.invalid);
+    }
+
+    #[test]
+    fn done() {
+        let mut env = env(false);
+        let rewards = env.config.rewards;
+        let (_, reward, done) = env.step(cell(&env, true));
+        assert_eq!((reward, done), (rewards.loss, true));
+        assert_eq!(env.step(cell(&env, false)), (env.observation(), rewards.invalid, true));
+        assert!(env.valid_actions().iter().all(|&v| !v));
+
+        //a zero cell opens the board around the single mine
+        env.reset(7);
+        let zero = env.game.map.iter().position(|cell| cell.mines == 0 && cell.val == 0).unwrap();
+        env.step(zero);
+        while !env.done {
+            let free = env.game.map.iter()
+                .position(|cell| cell.mines == 0 && cell.flag == CellTag::UNSELECTED).unwrap();
+            env.step(free);
+        }
+        assert!(env.won());
+    }
+
+    #[test]
+    fn valid_actions() {
+        let mut env = env(true);
+        let rewards = env.config.rewards;
+        assert_eq!(env.valid_actions(), vec![true; 32]);
+
+        //one mine, one flag: the other cells can't take one
+        assert_eq!(env.step(16).1, rewards.flag);
+        let valid = env.valid_actions();
+        assert!(!valid[0] && valid[16]);
+        assert!(valid[1..16].iter().all(|&v| v));
+        assert!(valid[17..].iter().all(|&v| !v));
+        assert_eq!(env.step(17).1, rewards.invalid);
+        assert_eq!(env.game.map[1].flag, CellTag::UNSELECTED);
+
+        //the flag comes back off
+        assert_eq!(env.step(16).1, rewards.flag);
+        assert_eq!(env.valid_actions(), vec![true; 32]);
+    }
+}
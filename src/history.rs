@@ -0,0 +1,87 @@
+use crate::engine::Cell;
+
+//game counters saved with every move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Counters {
+    pub selected: usize, //total cells selected
+    pub f_mines: usize, //flagged mines
+    pub state: u8, //0-playing 1-winner 2-looser
+}
+
+//the cells changed by a move, with their value before and after it
+#[derive(Clone)]
+struct Move {
+    id: usize, //the position after the move
+    cells: Vec<(usize, Cell, Cell)>,
+    before: Counters,
+    after: Counters,
+}
+
+//undo and redo stacks of the moves of a practice game
+#[derive(Default, Clone)]
+pub struct History {
+    undo: Vec<Move>,
+    redo: Vec<Move>,
+    moves: usize, //moves recorded, undone ones too
+    pub used: bool, //a move was undone in this game
+}
+
+impl History {
+    // keep the changes between the map before and after a move
+    // a new move drops the moves that could be redone
+    pub fn record(&mut self, before: &[Cell], b_counters: Counters,
+        after: &[Cell], a_counters: Counters) {
+        let cells: Vec<(usize, Cell, Cell)> = before.iter().zip(after)
+            .enumerate()
+            .filter(|(_, (b, a))| b != a)
+            .map(|(ind, (b, a))| (ind, *b, *a))
+            .collect();
+
+        if cells.is_empty() && b_counters == a_counters {
+            return;
+        }
+
+        self.moves += 1;
+        self.undo.push(Move { id: self.moves, cells, before: b_counters, after: a_counters });
+        self.redo.clear();
+    }
+
+    // where the game is in the history, the same after an undo and a redo,
+    // a new one after every new move
+    pub fn position(&self) -> usize {
+        self.undo.last().map_or(0, |m| m.id)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // take back the last move, returns the counters before it
+    pub fn undo(&mut self, map: &mut [Cell]) -> Option<Counters> {
+        let m = self.undo.pop()?;
+        for &(ind, before, _) in &m.cells {
+            map[ind] = before;
+        }
+        self.used = true;
+
+        let counters = m.before;
+        self.redo.push(m);
+        Some(counters)
+    }
+
+    // play again the last undone move, returns the counters after it
+    pub fn redo(&mut self, map: &mut [Cell]) -> Option<Counters> {
+        let m = self.redo.pop()?;
+        for &(ind, _, after) in &m.cells {
+            map[ind] = after;
+        }
+
+        let counters = m.after;
+        self.undo.push(m);
+        Some(counters)
+    }
+}
@@ -0,0 +1,187 @@
+use eframe::egui;
+use egui::Key;
+use serde::{Deserialize, Serialize};
+
+//board actions that can be bound to keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Reveal,
+    Flag,
+    Chord,
+    NewGame,
+    Welcome,
+    Pause,
+    Undo,
+    Redo,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::Up, Action::Down, Action::Left, Action::Right,
+        Action::Reveal, Action::Flag, Action::Chord,
+        Action::NewGame, Action::Welcome, Action::Pause,
+        Action::Undo, Action::Redo,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::Left => "move left",
+            Action::Right => "move right",
+            Action::Reveal => "reveal",
+            Action::Flag => "flag",
+            Action::Chord => "chord",
+            Action::NewGame => "new game",
+            Action::Welcome => "welcome window",
+            Action::Pause => "pause",
+            Action::Undo => "undo (practice)",
+            Action::Redo => "redo (practice)",
+        }
+    }
+}
+
+//keys bound to each action, persisted with the app storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: Vec<Key>,
+    pub down: Vec<Key>,
+    pub left: Vec<Key>,
+    pub right: Vec<Key>,
+    pub reveal: Vec<Key>,
+    pub flag: Vec<Key>,
+    pub chord: Vec<Key>,
+    pub new_game: Vec<Key>,
+    pub welcome: Vec<Key>,
+    pub pause: Vec<Key>,
+    pub undo: Vec<Key>,
+    pub redo: Vec<Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            up: vec![Key::ArrowUp, Key::K],
+            down: vec![Key::ArrowDown, Key::J],
+            left: vec![Key::ArrowLeft, Key::H],
+            right: vec![Key::ArrowRight, Key::L],
+            reveal: vec![Key::Space, Key::Enter],
+            flag: vec![Key::F],
+            chord: vec![Key::D],
+            new_game: vec![Key::N],
+            welcome: vec![Key::Escape],
+            pause: vec![Key::P],
+            undo: vec![Key::U],
+            redo: vec![Key::R],
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &Vec<Key> {
+        match action {
+            Action::Up => &self.up,
+            Action::Down => &self.down,
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Reveal => &self.reveal,
+            Action::Flag => &self.flag,
+            Action::Chord => &self.chord,
+            Action::NewGame => &self.new_game,
+            Action::Welcome => &self.welcome,
+            Action::Pause => &self.pause,
+            Action::Undo => &self.undo,
+            Action::Redo => &self.redo,
+        }
+    }
+
+    pub fn keys_mut(&mut self, action: Action) -> &mut Vec<Key> {
+        match action {
+            Action::Up => &mut self.up,
+            Action::Down => &mut self.down,
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Reveal => &mut self.reveal,
+            Action::Flag => &mut self.flag,
+            Action::Chord => &mut self.chord,
+            Action::NewGame => &mut self.new_game,
+            Action::Welcome => &mut self.welcome,
+            Action::Pause => &mut self.pause,
+            Action::Undo => &mut self.undo,
+            Action::Redo => &mut self.redo,
+        }
+    }
+
+    // actions whose keys were pressed this frame
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        ctx.input(|i| {
+            Action::ALL
+                .into_iter()
+                .filter(|&a| self.keys(a).iter().any(|&k| i.key_pressed(k)))
+                .collect()
+        })
+    }
+
+    // key bindings window, capture is the action waiting for a new key
+    // returns false when the window is closed
+    pub fn ui(&mut self, ctx: &egui::Context, capture: &mut Option<Action>) -> bool {
+        let mut open = true;
+
+        //a pressed key is bound to the action waiting for it
+        if let Some(action) = *capture {
+            let key = ctx.input(|i| i.events.iter().find_map(|e| match e {
+                egui::Event::Key { key, pressed: true, .. } => Some(*key),
+                _ => None,
+            }));
+
+            if let Some(key) = key {
+                //a key does a single action
+                for a in Action::ALL {
+                    self.keys_mut(a).retain(|&k| k != key);
+                }
+                self.keys_mut(action).push(key);
+                *capture = None;
+            }
+        }
+
+        egui::Window::new("Key bindings")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid::new("keys").striped(true).show(ui, |ui| {
+                    for action in Action::ALL {
+                        ui.label(action.name());
+
+                        let keys: Vec<&str> = self.keys(action).iter().map(|k| k.name()).collect();
+                        ui.label(keys.join(", "));
+
+                        if *capture == Some(action) {
+                            ui.label("press a key...");
+                        } else if ui.button("add").clicked() {
+                            *capture = Some(action);
+                        }
+
+                        if ui.button("clear").clicked() {
+                            self.keys_mut(action).clear();
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                if ui.button("Defaults").clicked() {
+                    *self = Self::default();
+                    *capture = None;
+                }
+            });
+
+        if !open {
+            *capture = None;
+        }
+
+        open
+    }
+}
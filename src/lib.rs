@@ -0,0 +1,12 @@
+//RustyMines game rules, shared by the gui and the terminal frontends
+pub mod bot;
+pub mod campaign;
+pub mod engine;
+pub mod env;
+pub mod history;
+pub mod puzzle;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod solver;
@@ -1,288 +1,359 @@
-use std::time::{SystemTime, Duration};
+use std::time::Duration;
 
 use eframe::egui;
 use egui::{Color32, FontFamily, FontId, TextStyle};
 use FontFamily::Proportional;
 use TextStyle::*;
 
-use rand::thread_rng;
-use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use rustymines::bot::{self, Match};
+use rustymines::campaign::{parse_campaign, Level};
+use rustymines::engine::{daily_game, get_mask, load_pbm, seed_from, utc_date, CellTag, Clock, Event, Game, Move, Preset, Shape};
+
+mod achievements;
+use achievements::Achievements;
+mod args;
+use args::{Args, ThemeArg};
+mod coop;
+use coop::Coop;
+mod editor;
+use editor::Editor;
+mod endless;
+use endless::Endless;
+mod keys;
+use keys::{Action, KeyBindings};
+mod net;
+mod puzzles;
+use puzzles::Puzzles;
+mod race;
+use race::Race;
+mod skin;
+use skin::{Skin, Tile};
+mod stats;
+use stats::{EndlessRun, GameResult, Results};
+mod theme;
+use theme::{Flavour, Look, Palette, ThemeFiles};
 
 const COLS: u16 = 20;
 const ROWS: u16 = 20;
 const MINES: u16 = 75;
 const CELL_SIZE: f32 = 30.0;
+const MIN_CELL_SIZE: f32 = 12.0;
+const MAX_CELL_SIZE: f32 = 80.0;
+//levels of the campaign
+const CAMPAIGN: &str = include_str!("../assets/campaign.toml");
 
-const UTF8_SMILE: [u8; 4] = [0xF0, 0x9F, 0x98, 0x90];
-const UTF8_WINNER: [u8; 4] = [0xF0, 0x9F, 0x98, 0x8E];
-const UTF8_LOOSER: [u8; 4] = [0xF0, 0x9F, 0x98, 0x9E];
-const UTF8_BOMB: [u8; 4] = [0xF0, 0x9F, 0x92, 0xA3];
-const UTF8_FLAG: [u8; 4] = [0xF0,0x9F,0x9A,0xA9];
-const UTF8_CROSS: [u8; 4] = [0xF0, 0x9F, 0x8E, 0x8C];
-const UTF8_DEAD: [u8; 4] = [0xF0, 0x9F, 0x95, 0xB1];
-//const UTF8_CHECK: [u8; 4] = [0x00, 0xE2, 0x9C, 0x85];
 
 fn main() {
 
-    // set the window frame viewport size
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) if args.help => {
+            println!("{}", args::USAGE);
+            return;
+        },
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("rustymines: {}\ntry 'rustymines --help' for the options", e);
+            std::process::exit(2);
+        },
+    };
+
+    //board size from the loaded board, the options or the defaults
+    let (rows, columns) = match &args.game {
+        Some(game) => (game.rows, game.columns),
+        None => (args.rows.unwrap_or(ROWS.into()), args.columns.unwrap_or(COLS.into())),
+    };
+    if let Err(e) = args.check_mines(rows, columns) {
+        eprintln!("rustymines: {}", e);
+        std::process::exit(2);
+    }
+
+    if args.bot_protocol {
+        let settings = Match {
+            rows,
+            columns,
+            n_mines: args.n_mines.unwrap_or(MINES.into()),
+            seed: args.seed.as_deref().and_then(seed_from).unwrap_or_else(|| thread_rng().gen()),
+            games: args.games.unwrap_or(1),
+            board: args.game,
+        };
+        if let Err(e) = play_bot(&settings, args.bot.as_deref()) {
+            eprintln!("rustymines: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // set the window frame viewport size, big boards scroll
     let mut options = eframe::NativeOptions::default();
     options.viewport.inner_size = Some(egui::Vec2 { 
-        x: (CELL_SIZE + 1.0) * f32::from(COLS) + 16.,
-        y: (CELL_SIZE + 1.0) * f32::from(ROWS) + 58.
+        x: (CELL_SIZE + 1.0) * columns.min(40) as f32 + 16.,
+        y: (CELL_SIZE + 1.0) * rows.min(30) as f32 + 82.
     });
     options.viewport.min_inner_size = Some(egui::Vec2 { 
         x: (CELL_SIZE + 1.0) * 4. + 16., 
-        y: (CELL_SIZE + 1.0) * 4. + 58.
+        y: (CELL_SIZE + 1.0) * 4. + 82.
     });
 
     let _ = eframe::run_native("RustyMines", options, 
-        Box::new(|cc| Ok(Box::new(AppGui::new(
-            cc, 
-            ROWS.into(), 
-            COLS.into(), 
-            MINES.into())))));
+        Box::new(|cc| Ok(Box::new(AppGui::new(cc, args)))));
 }
 
-/// The colors for a catppuccin theme variant.
-/// from https://github.com/catppuccin/egui
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
-pub struct MyTheme {
-    pub rosewater: Color32,
-    pub flamingo: Color32,
-    pub pink: Color32,
-    pub mauve: Color32,
-    pub red: Color32,
-    pub maroon: Color32,
-    pub peach: Color32,
-    pub yellow: Color32,
-    pub green: Color32,
-    pub teal: Color32,
-    pub sky: Color32,
-    pub sapphire: Color32,
-    pub blue: Color32,
-    pub lavender: Color32,
-    pub text: Color32,
-    pub subtext1: Color32,
-    pub subtext0: Color32,
-    pub overlay2: Color32,
-    pub overlay1: Color32,
-    pub overlay0: Color32,
-    pub surface2: Color32,
-    pub surface1: Color32,
-    pub surface0: Color32,
-    pub base: Color32,
-    pub mantle: Color32,
-    pub crust: Color32,
-    pub black: Color32,
-}
-//Latte colors from catppuccin
-pub const LATTE: MyTheme = MyTheme {
-    rosewater: Color32::from_rgb(220, 138, 120),
-    flamingo: Color32::from_rgb(221, 120, 120),
-    pink: Color32::from_rgb(234, 118, 203),
-    mauve: Color32::from_rgb(136, 57, 239),
-    red: Color32::from_rgb(210, 15, 57),
-    maroon: Color32::from_rgb(230, 69, 83),
-    peach: Color32::from_rgb(254, 100, 11),
-    yellow: Color32::from_rgb(223, 142, 29),
-    green: Color32::from_rgb(64, 160, 43),
-    teal: Color32::from_rgb(23, 146, 153),
-    sky: Color32::from_rgb(4, 165, 229),
-    sapphire: Color32::from_rgb(32, 159, 181),
-    blue: Color32::from_rgb(30, 102, 245),
-    lavender: Color32::from_rgb(114, 135, 253),
-    text: Color32::from_rgb(76, 79, 105),
-    subtext1: Color32::from_rgb(92, 95, 119),
-    subtext0: Color32::from_rgb(108, 111, 133),
-    overlay2: Color32::from_rgb(124, 127, 147),
-    overlay1: Color32::from_rgb(140, 143, 161),
-    overlay0: Color32::from_rgb(156, 160, 176),
-    surface2: Color32::from_rgb(172, 176, 190),
-    surface1: Color32::from_rgb(188, 192, 204),
-    surface0: Color32::from_rgb(204, 208, 218),
-    base: Color32::from_rgb(239, 241, 245),
-    mantle: Color32::from_rgb(230, 233, 239),
-    crust: Color32::from_rgb(220, 224, 232),
-    black: Color32::from_rgb(0, 0, 0),
-};
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum CellTag {
-    UNSELECTED, //unselected cell
-    FLAGGED,    //flagged cell
-    SELECTED,   //selected cell
-    DEAD,       //we got killed in this cell
+// play the match with the bot command, printing its stats,
+// or with a bot speaking over stdin and stdout
+fn play_bot(settings: &Match, command: Option<&str>) -> std::io::Result<()> {
+    let Some(command) = command else {
+        bot::run(settings, std::io::stdin().lock(), std::io::stdout().lock())?;
+        return Ok(());
+    };
+
+    let mut words = command.split_whitespace();
+    let mut child = std::process::Command::new(words.next().unwrap_or_default())
+        .args(words)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+
+    let input = std::io::BufReader::new(child.stdout.take().expect("piped stdout"));
+    //the bot's stdin closes with the match, don't leave it a zombie
+    let stats = bot::run(settings, input, child.stdin.take().expect("piped stdin"));
+    child.wait()?;
+
+    println!("{}", stats?.summary());
+    Ok(())
 }
 
-// cell data
-#[derive(Debug, Clone, Copy)]
-pub struct Cell {
-    val: u8, // number of neighbour mines(0..8) or 9 for mined
-    flag: CellTag,
-} 
+//paint a board cell with its label centered
+fn paint_cell(ui: &egui::Ui, rect: egui::Rect, lbl: &str, color: Color32, fill: Color32) {
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, fill);
 
-//get a new map: Vec<Cell> with len = rows * columns
-fn get_map_vec(rows: usize, columns: usize, n_mines: usize) -> Vec<Cell> {
-    let len = rows * columns;
-    //set the cells value to 0 and flagged with UNSELECTED
-    let mut m_vec: Vec<Cell> = vec![Cell{val: 0, flag: CellTag::UNSELECTED}; len];
+    if !lbl.is_empty() {
+        painter.text(rect.center(), egui::Align2::CENTER_CENTER, lbl,
+            FontId::new(rect.height() * 0.6, Proportional), color);
+    }
+}
 
-    //set mines in the first n_mines positions of the vector
-    for v in m_vec.iter_mut().take(n_mines) {
-        v.val = 9;
+//ctrl+scroll or pinch over a board widget zooms around the pointer
+//and dragging it pans the enclosing scroll area
+//origin is the board widget top left, returns the new cell size
+fn zoom_and_pan(ui: &egui::Ui, response: &egui::Response, origin: egui::Pos2, cell_size: f32) -> f32 {
+    if response.dragged() {
+        ui.scroll_with_delta(response.drag_delta());
     }
-    
-    //shuffle the vector
-    m_vec.shuffle(&mut thread_rng());
-    //shuffle again
-    m_vec.shuffle(&mut thread_rng());
-
-    // for each cell count the neighbouring mines
-    // at NW, W, SW, N, S, NE, E and SE postions
-    for r in 0..rows {
-        for c in 0..columns {
-       
-            let ind = (r * columns) + c;
-
-            //this cell is mined jump to the next one
-            if m_vec[ind].val == 9 {
-                continue;
-            }
-           
-            let mut count = 0;
-            //NW
-            if r > 0 && c > 0 && 
-                m_vec[((r-1)*columns) + (c-1)].val == 9 {
-                    count += 1;
-            }
-            //W
-            if c > 0 && 
-                m_vec[(r*columns) + (c-1)].val == 9 {
-                    count += 1;
-            }
-            //SW
-            if r < rows - 1 && c > 0 &&
-                m_vec[((r+1)*columns) + (c-1)].val == 9 {
-                    count += 1;
-            }
-            //N
-            if r > 0 &&
-                m_vec[((r-1)*columns) + c].val == 9 {
-                    count += 1;
-            }
-            //S
-            if r < rows - 1 &&
-                m_vec[((r+1)*columns) + c].val == 9 {
-                    count += 1;
-            }
-            //NE
-            if r > 0 && c < columns - 1 &&
-                m_vec[((r-1)*columns) + (c+1)].val == 9 {
-                    count += 1;
-            }
-            //E
-            if c < columns - 1 &&
-                m_vec[(r*columns) + (c+1)].val == 9 {
-                    count += 1;
-            }
-            //SE
-            if r < rows - 1 && c < columns - 1 &&
-                m_vec[((r+1)*columns) + (c+1)].val == 9 {
-                    count += 1;
-            } 
 
-            m_vec[ind].val = count;
+    let zoom = if response.contains_pointer() {
+        ui.input(|i| i.zoom_delta())
+    } else {
+        1.0
+    };
+    let size = (cell_size * zoom).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+
+    if size != cell_size {
+        //positions in the board scale with the cell step
+        if let Some(p) = response.hover_pos() {
+            let f = (size + 1.0) / (cell_size + 1.0);
+            ui.scroll_with_delta((p - origin) * (1.0 - f));
         }
     }
-       
-    m_vec
+
+    size
 }
 
 //App Data
 pub struct AppGui {
     welcome: bool, //display welcome menu
-    state: u8, //0-playing 1-winner 2-looser
-    s_time: SystemTime, //game start time
-    f_time: f64, //game time (elapsed seconds)
-    selected: usize, //total cells selected
+    clock: Clock, //game time
+    paused: bool, //the game is paused and the board hidden
+    s_practice: bool, //practice game in settings (welcome menu)
+    board: String, //description of the board in play, for the results
+    results: Results, //finished games and records
+    results_window: bool, //display the results window
+    recorded: Vec<usize>, //history positions of the finishes in the results
+    achievements: Achievements, //unlocked achievements, fed with the game events
+    achievements_window: bool, //display the achievements gallery
     s_rows: usize, //rows in settings (welcome menu)
     s_columns: usize, //columns in settings (welcome menu)
-    n_mines: usize, //total number of mines
-    f_mines: usize, //flagged mines
-    rows: usize, //map rows
-    columns: usize, //map columns
-    map: Vec<Cell>, //game map    
+    s_shape: Shape, //board shape in settings (welcome menu)
+    s_bitmap: String, //pbm file path in settings (welcome menu)
+    bitmap: Option<(usize, usize, Vec<bool>)>, //loaded pbm (rows, columns, mask)
+    bitmap_err: Option<String>, //last pbm load error
+    n_mines: usize, //total number of mines in settings (welcome menu)
+    max_mines: u8, //maximum number of mines in a cell in settings (welcome menu)
+    s_layers: usize, //layers in settings (welcome menu)
+    hovered: Option<usize>, //cell under the pointer in the last frame
+    cell_size: f32, //cell side in points (zoom)
+    fit: bool, //fit the board to the window in the next frame
+    cursor: usize, //keyboard cursor cell index
+    show_cursor: bool, //the keyboard was used since the last click
+    scroll_to_cursor: bool, //scroll the cursor into view in the next frame
+    keys: KeyBindings, //keys for the board actions
+    keys_window: bool, //display the key bindings window
+    capture: Option<Action>, //action waiting for a key in the key bindings window
+    palette: Palette, //number colors
+    flavour: Flavour, //catppuccin flavour
+    follow_system: bool, //use Latte or Mocha following the system light/dark preference
+    custom_theme: Option<String>, //theme file in use, overrides the flavour
+    theme_files: ThemeFiles, //theme files in the config directory
+    theme_err: Option<String>, //error exporting the current theme
+    look: Look, //colors and glyphs in use
+    skin_name: Option<String>, //skin in use, None draws the glyphs with fonts
+    skin: Option<Skin>, //loaded skin
+    skins_dir: Option<std::path::PathBuf>, //skin files directory
+    skin_err: Option<String>, //error loading the skin
+    s_density: f64, //endless board mine density in settings (welcome menu)
+    s_seed: String, //mines seed in settings, random if empty (welcome menu)
+    endless: Option<Endless>, //endless board, replaces the map while playing it
+    loaded: Option<(String, Game)>, //board from a file, new games restart it
+    race: Option<Race>, //lan race hosted or joined
+    coop: Option<Coop>, //lan co-op game hosted or joined
+    daily: Option<(String, bool)>, //date of the daily challenge in play, and ranked
+    editor: Option<Editor>, //board editor, replaces the board while editing
+    puzzles: Option<Puzzles>, //puzzle mode, its moves are scored
+    campaign: Vec<Level>, //campaign levels, in order
+    level: Option<usize>, //campaign level in play
+    level_msg: Option<String>, //how the last campaign level went
+    s_coop: bool, //host or join co-op instead of a race (welcome menu)
+    s_name: String, //player name in races (welcome menu)
+    s_addr: String, //race address to host on or join (welcome menu)
+    race_err: Option<String>, //why hosting or joining a lan game failed
+    game: Game, //the game in play
 }
 
 //eframe::egui stuff
 impl AppGui {
     //App creation
-    pub fn new(cc: &eframe::CreationContext<'_>,
-        rows: usize, 
-        columns: usize, 
-        n_mines: usize) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, args: Args) -> Self {
+        let rows = args.rows.unwrap_or(ROWS.into());
+        let columns = args.columns.unwrap_or(COLS.into());
+        let n_mines = args.n_mines.unwrap_or(MINES.into());
 
         let mut this = Self {
             welcome: true,
-            state: 0,
-            s_time: SystemTime::now(),
-            f_time: 0.0,
-            selected: 0,
+            clock: Clock::default(),
+            paused: false,
+            s_practice: false,
+            board: String::new(),
+            results: cc.storage
+                .and_then(|s| eframe::get_value(s, "results"))
+                .unwrap_or_default(),
+            results_window: false,
+            recorded: Vec::new(),
+            achievements: cc.storage
+                .and_then(|s| eframe::get_value(s, "achievements"))
+                .unwrap_or_default(),
+            achievements_window: false,
             s_rows: rows,
             s_columns: columns,
+            s_shape: Shape::Rectangle,
+            s_bitmap: String::new(),
+            bitmap: None,
+            bitmap_err: None,
             n_mines,
-            f_mines: 0,
-            rows,
-            columns,
-            map: get_map_vec(rows, columns, n_mines),
+            max_mines: 1,
+            s_layers: 1,
+            hovered: None,
+            cell_size: CELL_SIZE,
+            fit: false,
+            cursor: 0,
+            show_cursor: false,
+            scroll_to_cursor: false,
+            keys: cc.storage
+                .and_then(|s| eframe::get_value(s, "keys"))
+                .unwrap_or_default(),
+            keys_window: false,
+            capture: None,
+            palette: cc.storage
+                .and_then(|s| eframe::get_value(s, "palette"))
+                .unwrap_or(Palette::Catppuccin),
+            flavour: cc.storage
+                .and_then(|s| eframe::get_value(s, "flavour"))
+                .unwrap_or(Flavour::Latte),
+            follow_system: cc.storage
+                .and_then(|s| eframe::get_value(s, "follow_system"))
+                .unwrap_or(false),
+            custom_theme: cc.storage
+                .and_then(|s| eframe::get_value(s, "custom_theme"))
+                .unwrap_or(None),
+            theme_files: ThemeFiles::new("RustyMines"),
+            theme_err: None,
+            look: Look::new(Flavour::Latte.theme(), Palette::Catppuccin),
+            skin_name: cc.storage
+                .and_then(|s| eframe::get_value(s, "skin"))
+                .unwrap_or(None),
+            skin: None,
+            skins_dir: skin::skins_dir("RustyMines"),
+            skin_err: None,
+            s_density: 0.15,
+            s_seed: args.seed.unwrap_or_default(),
+            endless: None,
+            loaded: None,
+            race: None,
+            coop: None,
+            daily: None,
+            editor: None,
+            puzzles: None,
+            campaign: parse_campaign(CAMPAIGN).expect("a valid built-in campaign"),
+            level: None,
+            level_msg: None,
+            s_coop: false,
+            s_name: String::from("player"),
+            s_addr: String::from(net::DEFAULT_ADDR),
+            race_err: None,
+            game: Game::new(rows, columns, 1, n_mines, 1, &vec![true; rows * columns], 0),
         };
-        //set the default visuals and style
+
+        //the theme on the command line replaces the saved one
+        match args.theme {
+            Some(ThemeArg::Flavour(flavour)) => {
+                this.flavour = flavour;
+                this.follow_system = false;
+                this.custom_theme = None;
+            },
+            Some(ThemeArg::File(name)) => this.custom_theme = Some(name),
+            None => {},
+        }
+
+        if let Some(game) = args.game {
+            this.loaded = Some((args.board, game));
+            this.welcome = false;
+            this.new_game();
+        } else if args.no_welcome {
+            this.welcome = false;
+            this.new_game();
+        }
+
+        //set the visuals and style
+        this.theme_files.refresh();
+        this.look = this.current_look(None);
         this.set_visuals(&cc.egui_ctx);
 
         this
     }
 
-    // set the default visuals - LATTE from catppuccin
+    // look from the theme file in use, or from the flavour
+    // and the palette, following the system theme if asked
+    fn current_look(&self, system_theme: Option<eframe::Theme>) -> Look {
+        if let Some(Ok(custom)) = self.custom_theme.as_ref()
+            .and_then(|name| self.theme_files.get(name))
+            .map(|file| &file.theme) {
+            return custom.look(self.palette);
+        }
+
+        let theme = match system_theme {
+            Some(eframe::Theme::Dark) if self.follow_system => Flavour::Mocha.theme(),
+            _ if self.follow_system => Flavour::Latte.theme(),
+            _ => self.flavour.theme(),
+        };
+        Look::new(theme, self.palette)
+    }
+
+    // set the visuals from the current theme
     // and style
     pub fn set_visuals(&mut self, ctx: &egui::Context) {
 
-        let mut visuals = egui::Visuals::light();
-
-        visuals.override_text_color = Some(LATTE.text);
-        visuals.hyperlink_color = LATTE.rosewater;
-        visuals.faint_bg_color = LATTE.surface0;
-        visuals.extreme_bg_color = LATTE.crust;
-        visuals.code_bg_color = LATTE.mantle;
-        visuals.warn_fg_color = LATTE.peach;
-        visuals.error_fg_color = LATTE.maroon;
-        visuals.window_fill = LATTE.base;
-        visuals.panel_fill = LATTE.base;
-        visuals.window_stroke.color = LATTE.overlay1;
-        visuals.widgets.noninteractive.bg_fill = LATTE.base;
-        visuals.widgets.noninteractive.weak_bg_fill = LATTE.base;
-        visuals.widgets.noninteractive.bg_stroke.color = LATTE.overlay1;
-        visuals.widgets.noninteractive.fg_stroke.color = LATTE.text;
-        visuals.widgets.inactive.bg_fill = LATTE.surface0;
-        visuals.widgets.inactive.weak_bg_fill = LATTE.surface0;
-        visuals.widgets.inactive.bg_stroke.color = LATTE.overlay1;
-        visuals.widgets.inactive.fg_stroke.color = LATTE.text;
-        visuals.widgets.active.bg_fill = LATTE.surface1;
-        visuals.widgets.active.weak_bg_fill = LATTE.surface1;
-        visuals.widgets.active.bg_stroke.color = LATTE.overlay1;
-        visuals.widgets.active.fg_stroke.color = LATTE.text;
-        visuals.widgets.hovered.bg_fill = LATTE.surface2;
-        visuals.widgets.hovered.weak_bg_fill = LATTE.surface2;
-        visuals.widgets.hovered.bg_stroke.color = LATTE.overlay1;
-        visuals.widgets.hovered.fg_stroke.color = LATTE.text;
-        visuals.widgets.open.bg_fill = LATTE.surface0;
-        visuals.widgets.open.weak_bg_fill = LATTE.surface0;
-        visuals.widgets.open.bg_stroke.color = LATTE.overlay1;
-        visuals.widgets.open.fg_stroke.color = LATTE.text;
-        visuals.selection.bg_fill = LATTE.blue.linear_multiply(0.4);
-        visuals.selection.stroke.color = LATTE.overlay1;
-        visuals.window_shadow.color = LATTE.base;
-        visuals.popup_shadow.color = LATTE.base;
-        visuals.dark_mode = false;
+        let visuals = self.look.theme.visuals();
         
         let mut style = (*ctx.style()).clone();
 
@@ -300,119 +371,652 @@ impl AppGui {
         ctx.set_visuals(visuals);
     }
 
-    // set the map cell with index ind to SELECTED 
-    // and increment the App selected value
-    pub fn set_selected(&mut self, ind: usize) {
-        self.map[ind].flag = CellTag::SELECTED;
-        self.selected += 1;
-    }
     
-    //if a 0 cell is selected clean all neighbouring cells with 0 
-    //up to the first non zero cell
-    pub fn clean_neighbour_cells(&mut self, row: usize, column: usize) {
-        
-        // create a vector to push zero value cells to check neighbours
-        let mut cells: Vec<(usize, usize)> = Vec::new();
-        cells.push((row,column));
-
-        // pop the cells until the vector is empty
-        while let Some((r, c)) = cells.pop() {
-
-            let ind = (r * self.columns) + c;
-            
-            //if it's not a zero value cell set it to selected 
-            //and jump to the next one in the vector
-            if self.map[ind].val > 0 {
-                if self.map[ind].flag == CellTag::UNSELECTED {
-                    self.set_selected(ind);
-                }
-                continue;
-            }
+    // start a new game with the settings from the welcome menu,
+    // or restart the board loaded from a file
+    pub fn new_game(&mut self) {
+        self.leave_race_game();
+        self.daily = None;
+        self.editor = None;
+        self.puzzles = None;
+        self.level = None;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.endless = None;
+        self.cursor = 0;
+
+        if let Some((name, game)) = &self.loaded {
+            self.game = game.clone();
+            self.game.practice = self.s_practice;
+            self.board = format!("{} {}x{}, {} mines", name, game.rows, game.columns, game.n_mines);
+            return;
+        }
+
+        let (rows, columns, mask) = match (&self.bitmap, self.s_shape) {
+            (Some((rows, columns, mask)), Shape::Bitmap) =>
+                (*rows, *columns, mask.clone()),
+            _ => (self.s_rows, self.s_columns,
+                get_mask(self.s_shape, self.s_rows, self.s_columns)),
+        };
+        let seed = seed_from(&self.s_seed).unwrap_or_else(|| thread_rng().gen());
+        self.game = Game::new(rows, columns, self.s_layers,
+            self.n_mines, self.max_mines, &mask, seed);
+        self.game.practice = self.s_practice;
+        //the mines left room for a free cell
+        self.n_mines = self.game.n_mines;
+
+        self.board = format!("{} {}x{}", self.s_shape.name(), rows, columns);
+        if self.s_layers > 1 {
+            self.board += &format!("x{}", self.s_layers);
+        }
+        self.board += &format!(", {} mines", self.n_mines);
+        if self.max_mines > 1 {
+            self.board += &format!(" (up to {} per cell)", self.max_mines);
+        }
+    }
+
+    // start a new endless board with the settings from the welcome menu
+    pub fn new_endless(&mut self) {
+        self.leave_race_game();
+        self.daily = None;
+        self.editor = None;
+        self.puzzles = None;
+        self.level = None;
+        self.clock = Clock::default();
+        self.paused = false;
+        //the normal board stays behind the endless one
+        self.game.practice = false;
+        let seed = seed_from(&self.s_seed).unwrap_or_else(|| thread_rng().gen());
+        self.endless = Some(Endless::new(seed, self.s_density));
+        self.board = format!("endless, density {:.2}", self.s_density);
+    }
+
+    // start today's daily challenge, the first attempt is ranked
+    // and the retries are practice games
+    pub fn new_daily(&mut self) {
+        self.leave_race_game();
+        self.editor = None;
+        self.puzzles = None;
+        self.level = None;
+        let date = utc_date(std::time::SystemTime::now());
+        let ranked = !self.results.daily.contains_key(&date);
+
+        self.game = daily_game(&date);
+        self.game.practice = !ranked;
+        if ranked {
+            self.results.start_daily(&date);
+        }
+        self.board = format!("daily {}{}", date, if ranked { "" } else { " practice" });
+        self.daily = Some((date, ranked));
+
+        self.welcome = false;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.endless = None;
+        self.loaded = None;
+        self.cursor = 0;
+    }
+
+    // play the lan board in game, the timer starts with it
+    fn start_lan_game(&mut self, mode: &str) {
+        self.daily = None;
+        self.editor = None;
+        self.puzzles = None;
+        self.level = None;
+        self.board = format!("{} {}x{}", mode, self.game.rows, self.game.columns);
+        if self.game.layers > 1 {
+            self.board += &format!("x{}", self.game.layers);
+        }
+        self.board += &format!(", {} mines", self.game.n_mines);
+        self.welcome = false;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.endless = None;
+        self.loaded = None;
+        self.cursor = 0;
+    }
+
+    // edit a new board with the size from the welcome menu
+    fn new_editor(&mut self) {
+        self.leave_race_game();
+        self.daily = None;
+        self.puzzles = None;
+        self.level = None;
+        self.endless = None;
+        self.loaded = None;
+        self.welcome = false;
+        self.paused = false;
+        self.editor = Some(Editor::new(self.s_rows, self.s_columns));
+    }
+
+    // play the board in the editor, new games restart it
+    fn playtest(&mut self) {
+        let Some(mut editor) = self.editor.take() else { return };
+        if let Some(game) = editor.game() {
+            self.loaded = Some((String::from("puzzle"), game));
+            self.new_game();
+            editor.testing = true;
+        }
+        self.editor = Some(editor);
+    }
+
+    // play a puzzle of the pack, the built-in one to begin with
+    fn new_puzzle(&mut self, index: Option<usize>) {
+        self.leave_race_game();
+        let puzzles = match (self.puzzles.as_mut(), index) {
+            (Some(puzzles), Some(index)) => {
+                self.game = puzzles.start(index);
+                puzzles
+            },
+            _ => {
+                let (puzzles, game) = Puzzles::new();
+                self.game = game;
+                self.puzzles.insert(puzzles)
+            },
+        };
+        self.board = format!("puzzle {}", puzzles.name());
+
+        self.daily = None;
+        self.editor = None;
+        self.level = None;
+        self.welcome = false;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.endless = None;
+        self.loaded = None;
+        self.cursor = 0;
+    }
+
+    // play a campaign level, a new board each time
+    fn new_level(&mut self, index: usize) {
+        self.leave_race_game();
+        let level = &self.campaign[index];
+        self.game = level.game(thread_rng().gen());
+        self.board = format!("campaign {}", level.name);
+        self.level = Some(index);
+
+        self.daily = None;
+        self.editor = None;
+        self.puzzles = None;
+        self.welcome = false;
+        self.clock = Clock::default();
+        self.paused = false;
+        self.endless = None;
+        self.loaded = None;
+        self.cursor = 0;
+    }
+
+    // state of the board in play, 0 playing 1 winner 2 looser,
+    // an endless run is over at its first mine
+    fn state(&self) -> u8 {
+        match &self.endless {
+            Some(endless) => if endless.lost { 2 } else { 0 },
+            None => self.game.state,
+        }
+    }
+
+    // a level is open once the one before it is passed
+    fn unlocked(&self, index: usize) -> bool {
+        index == 0 || self.results.campaign.contains(&self.campaign[index - 1].name)
+    }
+
+    // a new game gives up the race game in play
+    // and leaves the co-op game, its board is gone
+    fn leave_race_game(&mut self) {
+        if let Some(race) = self.race.as_mut().filter(|r| r.racing()) {
+            race.finish(false);
+        }
+        self.coop = None;
+    }
+
+    // move the keyboard cursor
+    // left and right go on to the next layer at the board edges
+    fn move_cursor(&mut self, action: Action) {
+        let (mut l, mut r, mut c) = (
+            self.cursor / (self.game.rows * self.game.columns),
+            (self.cursor / self.game.columns) % self.game.rows,
+            self.cursor % self.game.columns);
+
+        match action {
+            Action::Up => r = r.saturating_sub(1),
+            Action::Down => r = (r + 1).min(self.game.rows - 1),
+            Action::Left if c > 0 => c -= 1,
+            Action::Left if l > 0 => (l, c) = (l - 1, self.game.columns - 1),
+            Action::Right if c < self.game.columns - 1 => c += 1,
+            Action::Right if l < self.game.layers - 1 => (l, c) = (l + 1, 0),
+            _ => {},
+        }
 
-            //if the neighbour cell it's not a mine flag it selected 
-            //and push it to the vector to have it's neighbours checked
+        self.cursor = (((l * self.game.rows) + r) * self.game.columns) + c;
+    }
 
-            //NW
-            if r > 0 && c > 0 {
-                let i = ((r - 1) * self.columns) + (c - 1);  
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r - 1, c - 1));
+    // do a board action from the keyboard
+    fn key_action(&mut self, action: Action) {
+        match action {
+            Action::Welcome => self.welcome = true,
+            Action::NewGame => {
+                self.welcome = false;
+                if self.endless.is_some() {
+                    self.new_endless();
+                } else if self.daily.is_some() {
+                    self.new_daily();
+                } else if self.editor.is_some() {
+                    self.playtest();
+                } else if let Some(puzzles) = &self.puzzles {
+                    self.new_puzzle(Some(puzzles.current));
+                } else if let Some(index) = self.level {
+                    self.new_level(index);
+                } else {
+                    self.new_game();
                 }
-            }
-            //W
-            if c > 0 {
-                let i = (r * self.columns) + (c - 1);  
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r, c - 1));
+            },
+            Action::Pause => {
+                if !self.welcome && self.state() == 0 {
+                    self.paused = !self.paused;
                 }
-            }
-            //SW
-            if r < self.rows - 1 && c > 0 {
-                let i = ((r + 1) * self.columns) + (c - 1);  
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r + 1, c - 1));
+            },
+            //a lost game can be undone
+            Action::Undo if self.game.practice && !self.welcome && !self.paused => self.game.undo(),
+            Action::Redo if self.game.practice && !self.welcome && !self.paused => self.game.redo(),
+            Action::Undo | Action::Redo => {},
+            //the cursor is only on the normal board while playing
+            _ if self.welcome || self.paused || self.game.state != 0 || self.endless.is_some()
+                || self.editor.as_ref().is_some_and(|e| !e.testing) => {},
+            Action::Up | Action::Down | Action::Left | Action::Right => {
+                self.move_cursor(action);
+                self.show_cursor = true;
+                self.scroll_to_cursor = true;
+            },
+            Action::Reveal => {
+                self.show_cursor = true;
+                self.scroll_to_cursor = true;
+                if self.game.map[self.cursor].flag == CellTag::UNSELECTED {
+                    self.do_move(Move::Reveal(self.cursor));
                 }
-            }
-            //N
-            if r > 0 {
-                let i = ((r - 1) * self.columns) + c;
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r - 1, c));
+            },
+            Action::Flag => {
+                self.show_cursor = true;
+                self.scroll_to_cursor = true;
+                self.do_move(Move::Flag(self.cursor, true));
+            },
+            Action::Chord => {
+                self.show_cursor = true;
+                self.scroll_to_cursor = true;
+                self.do_move(Move::Chord(self.cursor));
+            },
+        }
+    }
+
+    // screen reader description of the cell with index ind
+    fn describe_cell(&self, ind: usize) -> String {
+        let cell = &self.game.map[ind];
+        let (l, r, c) = (
+            ind / (self.game.rows * self.game.columns),
+            (ind / self.game.columns) % self.game.rows,
+            ind % self.game.columns);
+
+        let pos = if self.game.layers > 1 {
+            format!("layer {} row {} column {}", l + 1, r + 1, c + 1)
+        } else {
+            format!("row {} column {}", r + 1, c + 1)
+        };
+
+        let plural = |n: u8, what: &str| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" });
+        let status = match cell.flag {
+            CellTag::HOLE => "not part of the board".to_string(),
+            CellTag::UNSELECTED => "covered".to_string(),
+            CellTag::FLAGGED if self.game.state > 0 && cell.flags != cell.mines => "wrong flag".to_string(),
+            CellTag::FLAGGED if self.game.max_mines > 1 => format!("flagged, {}", plural(cell.flags, "flag")),
+            CellTag::FLAGGED => "flagged".to_string(),
+            CellTag::DEAD => "exploded mine".to_string(),
+            CellTag::SELECTED if cell.mines > 0 => format!("revealed, {}", plural(cell.mines, "mine")),
+            CellTag::SELECTED if cell.val == 0 => "revealed, no adjacent mines".to_string(),
+            CellTag::SELECTED => format!("revealed, {}", plural(cell.val, "adjacent mine")),
+        };
+
+        format!("{}, {}", pos, status)
+    }
+
+    // skin tile of the cell with index ind
+    // and the mine or flag count to show over it
+    fn cell_tile(&self, ind: usize) -> (Tile, u8) {
+        let cell = &self.game.map[ind];
+        let count = |n: u8| if self.game.max_mines > 1 { n } else { 0 };
+
+        match cell.flag {
+            CellTag::FLAGGED if self.game.state > 0 && cell.flags != cell.mines =>
+                (Tile::WrongFlag, count(cell.flags)),
+            CellTag::FLAGGED => (Tile::Flag, count(cell.flags)),
+            CellTag::SELECTED if cell.mines > 0 => (Tile::Mine, count(cell.mines)),
+            CellTag::SELECTED => (Tile::Number(cell.val), 0),
+            CellTag::DEAD => (Tile::Exploded, 0),
+            _ => (Tile::Covered, 0),
+        }
+    }
+
+    // label and text color of the cell with index ind
+    // and if it can still be clicked
+    // flags are red, or the color of the co-op player who put them
+    fn flag_color(&self, ind: usize) -> Color32 {
+        self.coop.as_ref()
+            .and_then(|coop| coop.owners.get(&ind))
+            .map_or(self.look.theme.red, |&id| coop::mate_color(id, &self.look))
+    }
+
+    fn cell_look(&self, ind: usize) -> (String, Color32, bool) {
+        let cell = &self.game.map[ind];
+
+        match cell.flag {
+            CellTag::FLAGGED => {
+                //if cell is flagged display a utf8 flag
+                let glyphs = &self.look.glyphs;
+                let mut lbl = glyphs.flag.clone();
+                let mut color = self.flag_color(ind);
+                if self.game.state > 0 {
+                    //if not playing
+                    if cell.flags != cell.mines {
+                        //display a utf8 cross if the flag
+                        // doesn't match the cell mines
+                        lbl = glyphs.cross.clone();
+                    } else {
+                        color = self.look.theme.green;
+                    }
                 }
-            }
-            //S
-            if r < self.rows - 1 {
-                let i = ((r + 1) * self.columns) + c;
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r + 1, c));
+
+                //the flag counter in multi-mine games
+                if self.game.max_mines > 1 {
+                    lbl = format!("{}{}", lbl, cell.flags);
                 }
-            }
-            //E
-            if c < self.columns - 1 {
-                let i = (r * self.columns) + (c + 1);
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r, c + 1));
+
+                (lbl, color, true)
+            },
+            CellTag::SELECTED => {
+                let lbl = if cell.mines > 0 {
+                    //it's a bomb, with the count if it has more than one
+                    let bomb = self.look.glyphs.bomb.clone();
+                    if cell.mines > 1 { format!("{}{}", bomb, cell.mines) } else { bomb }
+                } else if cell.val > 0 {
+                    //if it's not a bomb and it has a value
+                    //show it
+                    format!("{}", cell.val)
+                } else {
+                    String::new()
+                };
+
+                (lbl, self.look.number(cell.val), false)
+            },
+            CellTag::DEAD => (self.look.glyphs.dead.clone(), self.look.theme.red, false),
+            _ => (String::new(), self.look.theme.text, cell.flag == CellTag::UNSELECTED),
+        }
+    }
+
+    // size of the board widget, layers are side by side
+    // with half a cell between them
+    fn board_size(&self, cell_size: f32) -> egui::Vec2 {
+        let step = cell_size + 1.0;
+        let layer = self.game.columns as f32 * step + cell_size / 2.;
+        egui::Vec2::new(self.game.layers as f32 * layer - cell_size / 2., self.game.rows as f32 * step)
+    }
+
+    // cell size to show the whole board in the available size
+    fn fit_cell_size(&self, available: egui::Vec2) -> f32 {
+        //width = layers * columns * (size + 1) + (layers - 1) * size / 2
+        //height = rows * (size + 1)
+        let cells = (self.game.layers * self.game.columns) as f32;
+        let gaps = (self.game.layers - 1) as f32 / 2.;
+        let x = (available.x - cells) / (cells + gaps);
+        let y = available.y / self.game.rows as f32 - 1.0;
+        x.min(y).floor().clamp(MIN_CELL_SIZE, MAX_CELL_SIZE)
+    }
+
+    // index of the cell at pos (relative to the board widget)
+    fn cell_at(&self, pos: egui::Vec2) -> Option<usize> {
+        let step = self.cell_size + 1.0;
+        let layer = self.game.columns as f32 * step + self.cell_size / 2.;
+        if pos.x < 0. || pos.y < 0. {
+            return None;
+        }
+
+        let l = (pos.x / layer) as usize;
+        let c = ((pos.x - l as f32 * layer) / step) as usize;
+        let r = (pos.y / step) as usize;
+        if l >= self.game.layers || c >= self.game.columns || r >= self.game.rows {
+            return None;
+        }
+
+        Some((((l * self.game.rows) + r) * self.game.columns) + c)
+    }
+
+    // the board widget: paints the cells inside the viewport
+    // and hit tests the pointer against the cell grid
+    fn board_ui(&mut self, ui: &mut egui::Ui, viewport: egui::Rect) {
+        let step = self.cell_size + 1.0;
+        let layer = self.game.columns as f32 * step + self.cell_size / 2.;
+
+        //the viewport is relative to the scroll area content
+        let viewport = viewport.translate(ui.max_rect().min.to_vec2());
+        let (rect, response) = ui.allocate_exact_size(
+            self.board_size(self.cell_size), egui::Sense::click_and_drag());
+        let origin = rect.min;
+
+        let pointer = response.hover_pos().and_then(|p| self.cell_at(p - origin));
+        let playing = !self.welcome && self.game.state == 0;
+
+        //in 3D boards highlight the neighbours of the hovered cell
+        let highlight = match self.hovered {
+            Some(ind) if self.game.layers > 1 =>
+                self.game.neighbours(ind),
+            _ => Vec::new(),
+        };
+
+        //visible rows and columns
+        let first = |v: f32| (v.max(0.) / step) as usize;
+        let rows = first(viewport.min.y - origin.y)..=first(viewport.max.y - origin.y);
+
+        for l in 0..self.game.layers {
+            let x = origin.x + l as f32 * layer;
+            let columns = first(viewport.min.x - x)..=first(viewport.max.x - x);
+
+            for r in rows.clone().take_while(|&r| r < self.game.rows) {
+                for c in columns.clone().take_while(|&c| c < self.game.columns) {
+                    let ind = (((l * self.game.rows) + r) * self.game.columns) + c;
+
+                    //holes keep their place in the grid but aren't painted
+                    if self.game.map[ind].flag == CellTag::HOLE {
+                        continue;
+                    }
+
+                    let (lbl, color, enable) = self.cell_look(ind);
+                    let hovered = enable && playing && pointer == Some(ind);
+                    let fill = if highlight.contains(&ind) {
+                        self.look.theme.yellow.linear_multiply(0.4)
+                    } else if !enable {
+                        self.look.theme.mantle
+                    } else if hovered {
+                        self.look.theme.surface2
+                    } else {
+                        self.look.theme.surface0
+                    };
+
+                    let cell = egui::Rect::from_min_size(
+                        egui::Pos2::new(x + c as f32 * step, origin.y + r as f32 * step),
+                        egui::Vec2::new(self.cell_size, self.cell_size));
+
+                    let Some(skin) = &self.skin else {
+                        paint_cell(ui, cell, &lbl, color, fill);
+                        continue;
+                    };
+
+                    //skins are tinted for the highlights
+                    let (tile, count) = self.cell_tile(ind);
+                    skin.paint(ui.painter(), cell, tile);
+                    if highlight.contains(&ind) || hovered {
+                        ui.painter().rect_filled(cell, 0.0, fill.linear_multiply(0.5));
+                    }
+                    if count > 0 {
+                        ui.painter().text(cell.right_bottom(), egui::Align2::RIGHT_BOTTOM,
+                            count.to_string(), FontId::new(self.cell_size * 0.4, Proportional), color);
+                    }
+                    //co-op flags are outlined with the color of their player
+                    if self.coop.as_ref().is_some_and(|coop| coop.owners.contains_key(&ind)) {
+                        ui.painter().rect_stroke(cell.shrink(1.), 2.0, egui::Stroke::new(2., color));
+                    }
                 }
             }
-            //NE
-            if r > 0 && c < self.columns - 1 {
-                let i = ((r - 1) * self.columns) + (c + 1);  
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r - 1, c + 1));
+        }
+
+        let cell_rect = |ind: usize| {
+            let (l, r, c) = self.game.position(ind);
+            egui::Rect::from_min_size(
+                origin + egui::Vec2::new(l as f32 * layer + c as f32 * step, r as f32 * step),
+                egui::Vec2::new(self.cell_size, self.cell_size))
+        };
+
+        //the cursors of the other co-op players
+        if let Some(coop) = self.coop.as_ref().filter(|_| playing) {
+            for (id, mate) in coop.mates.iter().filter(|(&id, m)| Some(id) != coop.me && !m.left) {
+                if let Some(ind) = mate.cursor.filter(|&ind| ind < self.game.map.len()) {
+                    ui.painter().rect_stroke(cell_rect(ind).expand(1.), 2.0,
+                        egui::Stroke::new(2.5, coop::mate_color(*id, &self.look)));
                 }
             }
-            //SE
-            if r < self.rows - 1 && c < self.columns - 1 {
-                let i = ((r + 1) * self.columns) + (c + 1);  
-                if self.map[i].val < 9 && self.map[i].flag == CellTag::UNSELECTED {
-                    self.set_selected(i);
-                    cells.push((r + 1, c + 1));
-                }
+        }
+
+        //the keyboard cursor outline
+        if self.show_cursor && playing {
+            let cell = cell_rect(self.cursor);
+
+            ui.painter().rect_stroke(cell.expand(1.), 2.0, egui::Stroke::new(2.5, self.look.theme.peach));
+            if self.scroll_to_cursor {
+                ui.scroll_to_rect(cell.expand(self.cell_size), None);
+                //screen readers follow the focused board
+                response.request_focus();
             }
-        }    
-        
+        }
+        self.scroll_to_cursor = false;
+
+        //the board reads as the cell under the keyboard cursor
+        //and keeps the arrow keys while focused
+        let label = self.describe_cell(self.cursor);
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, playing, &label));
+        ui.memory_mut(|m| m.set_focus_lock_filter(response.id, egui::EventFilter {
+            horizontal_arrows: true,
+            vertical_arrows: true,
+            ..Default::default()
+        }));
+
+        self.hovered = pointer;
+        self.cell_size = zoom_and_pan(ui, &response, origin, self.cell_size);
+
+        let Some(ind) = pointer.filter(|_| playing) else {
+            return;
+        };
+
+        //the mouse moves the keyboard cursor and hides it
+        //space and enter on the focused board are keyboard actions, not clicks
+        let clicked = response.clicked_by(egui::PointerButton::Primary);
+        if clicked || response.secondary_clicked() {
+            self.cursor = ind;
+            self.show_cursor = false;
+        }
+
+        //right click adds a flag, wrapping back to
+        //unflagged after max_mines
+        //the scroll wheel adds or removes a flag
+        let scroll = if response.hovered() && self.game.max_mines > 1 {
+            ui.input(|i| i.raw_scroll_delta.y)
+        } else {
+            0.0
+        };
+        let secondary = response.secondary_clicked();
+
+        let enable = self.cell_look(ind).2;
+
+        if clicked && enable {
+            self.do_move(Move::Reveal(ind));
+        } else if secondary || scroll > 0.0 {
+            self.do_move(Move::Flag(ind, secondary));
+        } else if scroll < 0.0 {
+            self.do_move(Move::Unflag(ind));
+        }
     }
 
-    fn check_looser_map(&mut self) {
-        //set all unselected cells to selected
-        for cell in self.map.iter_mut() {
-            if cell.flag == CellTag::UNSELECTED {
-                cell.flag = CellTag::SELECTED;
-            }
+    // do a move on the board, the other racers get it too
+    fn do_move(&mut self, mv: Move) {
+        //puzzle moves are scored
+        if let Some(puzzles) = &mut self.puzzles {
+            puzzles.attempt.play(&mut self.game, mv);
+            return;
+        }
+
+        //co-op moves are played by the host
+        if let Some(coop) = self.coop.as_mut().filter(|c| c.started) {
+            coop.play(&mut self.game, mv);
+            return;
+        }
+
+        self.game.play(|game| game.apply(mv));
+        if let Some(race) = &mut self.race {
+            race.send_move(mv);
         }
     }
+
 }
 
 impl eframe::App for AppGui {
     
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, "keys", &self.keys);
+        eframe::set_value(storage, "palette", &self.palette);
+        eframe::set_value(storage, "flavour", &self.flavour);
+        eframe::set_value(storage, "follow_system", &self.follow_system);
+        eframe::set_value(storage, "custom_theme", &self.custom_theme);
+        eframe::set_value(storage, "skin", &self.skin_name);
+        eframe::set_value(storage, "results", &self.results);
+        eframe::set_value(storage, "achievements", &self.achievements);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+
+        //apply the chosen theme, eframe resets the visuals
+        //when the system theme changes
+        //theme files are reloaded when they change
+        self.theme_files.refresh();
+        let look = self.current_look(frame.info().system_theme);
+        if look != self.look || ctx.style().visuals.panel_fill != look.theme.base {
+            self.look = look;
+            self.set_visuals(ctx);
+        }
+
+        //load the chosen skin, going back to the fonts if it fails
+        if self.skin.as_ref().map(|s| &s.name) != self.skin_name.as_ref() {
+            self.skin = None;
+            if let Some(name) = &self.skin_name {
+                match Skin::open(ctx, name, self.skins_dir.as_deref()) {
+                    Ok(skin) => self.skin = Some(skin),
+                    Err(e) => {
+                        self.skin_err = Some(e);
+                        self.skin_name = None;
+                    },
+                }
+            }
+        }
+
+        //the clock only runs while playing, losing the window focus pauses the game
+        let playing = !self.welcome && self.state() == 0;
+        if playing && !ctx.input(|i| i.focused) {
+            self.paused = true;
+        }
+        self.clock.run(playing && !self.paused);
+
+        //keyboard control, off while editing the bindings or typing
+        if self.keys_window {
+            self.keys_window = self.keys.ui(ctx, &mut self.capture);
+        } else if !ctx.wants_keyboard_input() {
+            for action in self.keys.pressed(ctx) {
+                self.key_action(action);
+            }
+        }
 
         //display the welcome window
         if self.welcome {
@@ -420,49 +1024,286 @@ impl eframe::App for AppGui {
                 .show(ctx, |ui| {                    
 
                     ui.label("Settings:");
+
+                    egui::ComboBox::from_label("board shape")
+                        .selected_text(self.s_shape.name())
+                        .show_ui(ui, |ui| {
+                            for shape in Shape::ALL {
+                                ui.selectable_value(&mut self.s_shape, shape, shape.name());
+                            }
+                        });
+
+                    if self.s_shape == Shape::Bitmap {
+                        //the bitmap sets the number of rows and columns
+                        ui.horizontal(|ui| {
+                            ui.add_sized(
+                                egui::Vec2::new(200., 24.),
+                                egui::TextEdit::singleline(&mut self.s_bitmap)
+                                    .hint_text("file.pbm"));
+
+                            if ui.button("Load").clicked() {
+                                match load_pbm(self.s_bitmap.trim()) {
+                                    Ok(bitmap) => {
+                                        self.bitmap = Some(bitmap);
+                                        self.bitmap_err = None;
+                                    },
+                                    Err(e) => self.bitmap_err = Some(e),
+                                }
+                            }
+                        });
+
+                        if let Some(e) = &self.bitmap_err {
+                            ui.colored_label(self.look.theme.red, e);
+                        } else if let Some((rows, columns, _)) = &self.bitmap {
+                            ui.label(format!("{} rows x {} columns", rows, columns));
+                        }
+                    } else {
+                        ui.add_sized(
+                            egui::Vec2::new(250., 30.),
+                            egui::Slider::new(&mut self.s_rows, 4..=500)
+                                .text("number of rows"));
+
+                        ui.add_sized(
+                            egui::Vec2::new(250., 30.),
+                            egui::Slider::new(&mut self.s_columns, 4..=500)
+                                .text("number of columns"));
+                    }
                     
                     ui.add_sized(
                         egui::Vec2::new(250., 30.),
-                        egui::Slider::new(&mut self.s_rows, 4..=40)
-                            .text("number of rows"));
-                    
+                        egui::Slider::new(&mut self.s_layers, 1..=5)
+                            .text("number of layers"));
+
                     ui.add_sized(
                         egui::Vec2::new(250., 30.),
-                        egui::Slider::new(&mut self.s_columns, 4..=40)
-                            .text("number of columns"));
-                    
+                        egui::Slider::new(&mut self.n_mines, 1..=250_000)
+                            .logarithmic(true)
+                            .text("number of mines"));
+
                     ui.add_sized(
                         egui::Vec2::new(250., 30.),
-                        egui::Slider::new(&mut self.n_mines, 1..=400)
-                            .text("number of mines"));
+                        egui::Slider::new(&mut self.max_mines, 1..=4)
+                            .text("mines per cell"));
                     
+                    ui.horizontal(|ui| {
+                        for preset in Preset::ALL {
+                            if ui.button(preset.name()).clicked() {
+                                (self.s_rows, self.s_columns, self.n_mines) = preset.board();
+                                self.s_shape = Shape::Rectangle;
+                                self.s_layers = 1;
+                                self.max_mines = 1;
+                            }
+                        }
+                    });
+
+                    ui.collapsing("Campaign", |ui| {
+                        let mut play = None;
+                        egui::Grid::new("campaign").striped(true).show(ui, |ui| {
+                            for (index, level) in self.campaign.iter().enumerate() {
+                                ui.label(&level.name).on_hover_text(level.text());
+                                if self.results.campaign.contains(&level.name) {
+                                    ui.colored_label(self.look.theme.green, "passed");
+                                } else if !self.unlocked(index) {
+                                    ui.label("locked");
+                                } else {
+                                    ui.label("");
+                                }
+                                if ui.add_enabled(self.unlocked(index), egui::Button::new("Play").small()).clicked() {
+                                    play = Some(index);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                        if let Some(msg) = &self.level_msg {
+                            ui.label(msg);
+                        }
+                        if let Some(index) = play {
+                            self.new_level(index);
+                        }
+                    });
+
+                    ui.checkbox(&mut self.s_practice, "practice game, moves can be undone");
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            egui::Vec2::new(200., 24.),
+                            egui::TextEdit::singleline(&mut self.s_seed)
+                                .hint_text("random"));
+                        ui.label("seed");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("LAN game:");
+                        ui.radio_value(&mut self.s_coop, false, "race");
+                        ui.radio_value(&mut self.s_coop, true, "co-op");
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.add_sized(
+                            egui::Vec2::new(120., 24.),
+                            egui::TextEdit::singleline(&mut self.s_name));
+                        ui.add_sized(
+                            egui::Vec2::new(150., 24.),
+                            egui::TextEdit::singleline(&mut self.s_addr));
+                    });
+
+                    ui.horizontal(|ui| {
+                        let name = match self.s_name.trim() {
+                            "" => "player",
+                            name => name,
+                        };
+                        let (host, join) = (ui.button("Host").clicked(), ui.button("Join").clicked());
+                        if host || join {
+                            //one lan game at a time
+                            self.race = None;
+                            self.coop = None;
+                            let addr = self.s_addr.trim();
+                            let result = match (self.s_coop, host) {
+                                (false, true) => Race::host(addr, name).map(|r| self.race = Some(r)),
+                                (false, false) => Race::join(addr, name).map(|r| self.race = Some(r)),
+                                (true, true) => Coop::host(addr, name).map(|c| self.coop = Some(c)),
+                                (true, false) => Coop::join(addr, name).map(|c| self.coop = Some(c)),
+                            };
+                            self.race_err = result.err();
+                        }
+                        ui.label("name and address");
+                    });
+
+                    if let Some(e) = &self.race_err {
+                        ui.colored_label(self.look.theme.red, e);
+                    }
+
+                    ui.label("Endless board:");
+
+                    ui.add_sized(
+                        egui::Vec2::new(250., 30.),
+                        egui::Slider::new(&mut self.s_density, 0.10..=0.30)
+                            .text("mine density"));
+
                     ui.horizontal(|ui| { 
-                        if ui.button("OK").clicked() {
+                        let ready = self.s_shape != Shape::Bitmap || self.bitmap.is_some();
+                        if ui.add_enabled(ready, egui::Button::new("OK")).clicked() {
                             self.welcome = false;
-                            //make sure m_mines isn't bigger than the map size
-                            let len = self.s_rows * self.s_columns;
-                            if self.n_mines >= len {
-                                self.n_mines= len - 1;
-                            }
-                            self.rows = self.s_rows;
-                            self.columns = self.s_columns;
-                            self.selected = 0;
-                            self.f_mines = 0;
-                            self.f_time = 0.0;
-                            self.state = 0;
-                            self.map = get_map_vec(self.rows, self.columns, self.n_mines);
-                            self.s_time = SystemTime::now();
+                            self.loaded = None;
+                            self.new_game();
+                        }
+
+                        //today's challenge, one ranked attempt then practice
+                        let today = utc_date(std::time::SystemTime::now());
+                        let hint = match self.results.daily.get(&today) {
+                            None => String::from("one ranked attempt, the same board for everyone today"),
+                            Some(daily) => match daily.time {
+                                Some(time) if daily.won => format!("won in {:.1}s, retries are practice", time),
+                                _ => String::from("played, retries are practice"),
+                            },
+                        };
+                        if ui.button("Daily").on_hover_text(hint).clicked() {
+                            self.new_daily();
+                        }
+
+                        if ui.button("Endless").clicked() {
+                            self.welcome = false;
+                            self.loaded = None;
+                            self.new_endless();
+                        }
+
+                        if ui.button("Puzzles").clicked() {
+                            self.new_puzzle(None);
+                        }
+
+                        if ui.button("Editor").clicked() {
+                            self.new_editor();
                         }
 
                         if ui.button("Defaults").clicked() {
                             self.n_mines = MINES.into();
                             self.s_rows = ROWS.into();
                             self.s_columns = COLS.into();
+                            self.s_layers = 1;
+                            self.s_shape = Shape::Rectangle;
+                            self.max_mines = 1;
+                            self.s_density = 0.15;
+                            self.s_seed.clear();
                         }
                     });
                 });
         } //end if self.welcome
 
+        //settings menu
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("Settings", |ui| {
+                    ui.label("Theme:");
+                    ui.add_enabled_ui(self.custom_theme.is_none() && !self.follow_system, |ui| {
+                        for flavour in Flavour::ALL {
+                            ui.radio_value(&mut self.flavour, flavour, flavour.name());
+                        }
+                    });
+                    ui.add_enabled_ui(self.custom_theme.is_none(), |ui| {
+                        ui.checkbox(&mut self.follow_system, "follow the system light/dark theme");
+                    });
+
+                    //theme files, the broken ones show why
+                    ui.radio_value(&mut self.custom_theme, None, "built-in theme");
+                    for file in &self.theme_files.files {
+                        match &file.theme {
+                            Ok(_) => {
+                                ui.radio_value(&mut self.custom_theme,
+                                    Some(file.name.clone()), &file.name);
+                            },
+                            Err(e) => {
+                                ui.colored_label(self.look.theme.red,
+                                    format!("{}.toml: {}", file.name, e));
+                            },
+                        }
+                    }
+                    if ui.button("Export current theme").clicked() {
+                        self.theme_err = self.theme_files.export(&self.look).err();
+                    }
+                    if let Some(dir) = &self.theme_files.dir {
+                        ui.small(format!("theme files: {}", dir.display()));
+                    }
+                    if let Some(e) = &self.theme_err {
+                        ui.colored_label(self.look.theme.red, e);
+                    }
+
+                    ui.separator();
+                    ui.label("Skin:");
+                    ui.radio_value(&mut self.skin_name, None, "none, fonts and theme colors");
+                    ui.radio_value(&mut self.skin_name, Some(String::from(skin::DEFAULT_SKIN)), "classic");
+                    for name in skin::skin_names(self.skins_dir.as_deref()) {
+                        ui.radio_value(&mut self.skin_name, Some(name.clone()), &name);
+                    }
+                    if let Some(dir) = &self.skins_dir {
+                        ui.small(format!("skin files: {}", dir.display()));
+                    }
+                    if let Some(e) = &self.skin_err {
+                        ui.colored_label(self.look.theme.red, e);
+                    }
+
+                    ui.separator();
+                    ui.label("Number colors:");
+                    for palette in Palette::ALL {
+                        ui.radio_value(&mut self.palette, palette, palette.name());
+                    }
+
+                    ui.separator();
+                    if ui.button("Results...").clicked() {
+                        self.results_window = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Achievements...").clicked() {
+                        self.achievements_window = true;
+                        ui.close_menu();
+                    }
+                    if ui.button("Key bindings...").clicked() {
+                        self.keys_window = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
         //the main window
         egui::CentralPanel::default()
             .show(ctx, |ui| {
@@ -471,9 +1312,14 @@ impl eframe::App for AppGui {
                 ui.columns(3, |columns| {
                     
                     columns[0].vertical_centered(|ui| {
-                        ui.label(format!("Mines: {} of {}\nSelected {} of {}", 
-                                self.f_mines, self.n_mines,
-                                self.selected, self.map.len()));
+                        if let Some(endless) = &self.endless {
+                            ui.label(format!("Flags: {}\nCleared {}",
+                                    endless.flagged, endless.cleared));
+                        } else {
+                            ui.label(format!("Mines: {} of {}\nSelected {} of {}", 
+                                    self.game.f_mines, self.game.n_mines,
+                                    self.game.selected, self.game.cells));
+                        }
                     });
 
                     columns[1].vertical_centered( |ui| {
@@ -482,157 +1328,237 @@ impl eframe::App for AppGui {
                             egui::FontId::new(30.0, eframe::epaint::FontFamily::Proportional),
                         );
                         
-                        ui.visuals_mut().override_text_color = Some(LATTE.black);   
-                        let smile = match self.state {
-                            1 => UTF8_WINNER,
-                            2 => UTF8_LOOSER,
-                            _ => UTF8_SMILE,
+                        ui.visuals_mut().override_text_color = Some(self.look.theme.black);   
+                        let response = if let Some(skin) = &self.skin {
+                            let tile = match self.state() {
+                                1 => Tile::Winner,
+                                2 => Tile::Looser,
+                                _ => Tile::Smile,
+                            };
+                            ui.add(egui::ImageButton::new(skin.image(tile, egui::Vec2::new(32., 32.))))
+                        } else {
+                            let lbl = match self.state() {
+                                1 => self.look.glyphs.winner.clone(),
+                                2 => self.look.glyphs.looser.clone(),
+                                _ => self.look.glyphs.smile.clone(),
+                            };
+                            ui.add_sized((40.,40.), egui::Button::new(lbl))
                         };
-
-                        let lbl = String::from_utf8(smile.to_vec()).unwrap();
-
-                        if ui.add_sized((40.,40.), 
-                            egui::Button::new(lbl)).clicked() {
+                        if response.clicked() {
                             self.welcome = true;
                         }
+
+                        //the game state is a live region, screen readers
+                        //announce the win or the loss when it changes
+                        let status = match self.state() {
+                            1 => "You won!",
+                            2 => "You lost!",
+                            _ => "Playing",
+                        };
+                        response.widget_info(|| egui::WidgetInfo::labeled(
+                            egui::WidgetType::Button, true, format!("{}, open the welcome window", status)));
+                        ui.ctx().accesskit_node_builder(response.id, |builder| {
+                            builder.set_live(egui::accesskit::Live::Assertive);
+                        });
                     });
                     
                     columns[2].vertical_centered( |ui| {
                         
                         ui.ctx().request_repaint_after(Duration::new(1, 0));
                         
-                        if !self.welcome && self.state == 0 {
-                            self.f_time = self.s_time.elapsed().unwrap().as_secs_f64();
+                        ui.label(format!("Time: {:.0}", self.clock.elapsed().as_secs_f64()));
+
+                        ui.horizontal(|ui| {
+                            let lbl = if self.paused { "resume" } else { "pause" };
+                            if ui.add_enabled(!self.welcome && self.state() == 0,
+                                egui::Button::new(lbl).small()).clicked() {
+                                self.paused = !self.paused;
+                            }
+                            if ui.add_enabled(self.endless.is_none(),
+                                egui::Button::new("fit to window").small()).clicked() {
+                                self.fit = true;
+                            }
+                        });
+
+                        if self.game.practice {
+                            ui.horizontal(|ui| {
+                                let enabled = !self.welcome && !self.paused;
+                                if ui.add_enabled(enabled && self.game.history.can_undo(),
+                                    egui::Button::new("undo").small()).clicked() {
+                                    self.game.undo();
+                                }
+                                if ui.add_enabled(enabled && self.game.history.can_redo(),
+                                    egui::Button::new("redo").small()).clicked() {
+                                    self.game.redo();
+                                }
+                            });
                         }
-                        ui.label(format!("Time: {:.0}", self.f_time));
                     });
                 
                 });
                 
-                //map widgets
-                egui::ScrollArea::vertical().hscroll(true).show(ui, |ui| {
+                //the board is hidden while paused
+                if self.paused {
+                    ui.centered_and_justified(|ui| {
+                        if ui.button("Paused, click to resume").clicked() {
+                            self.paused = false;
+                        }
+                    });
+                    return;
+                }
 
-                    //disable if not playing
-                    if self.welcome || self.state != 0 {
-                        ui.disable();
-                    }
+                //the board being edited
+                if let Some(editor) = self.editor.as_mut().filter(|e| !e.testing) {
+                    self.cell_size = editor.ui(ui, self.cell_size, &self.look);
+                    return;
+                }
 
-                    for r in 0..self.rows {
-                        ui.horizontal(|ui| {
-                            for c in 0..self.columns {
-                                
-                                let ind = (r * self.columns) + c;
-                                let val = self.map[ind].val;
-                                let mut lbl = " ".to_string();
-                                let mut enable = true;
-
-                                if self.map[ind].flag == CellTag::FLAGGED {
-                                    ui.visuals_mut().override_text_color = 
-                                        Some(LATTE.red);   
-                                                                                       
-                                    //if cell is flagged display a utf8 flag
-                                    let mut flag = UTF8_FLAG;
-                                    if self.state > 0 {
-                                        //if not playing
-                                        if self.map[ind].val < 9 {
-                                            ui.visuals_mut().override_text_color = 
-                                                Some(LATTE.red);
-                                            //display a utf8 cross if the flag
-                                            // is not on a mine
-                                            flag = UTF8_CROSS;
-                                        
-                                        } else {
-                                            ui.visuals_mut().override_text_color = 
-                                                Some(LATTE.green);
-                                            
-                                            //or a utf8 check if it is
-                                            //flag = UTF8_CROSS;
-                                        }
-                                    }
-
-                                    lbl = String::from_utf8(flag.to_vec()).unwrap();
-
-                                } else if self.map[ind].flag == CellTag::SELECTED {
-                                    ui.visuals_mut().override_text_color = 
-                                            match val {
-                                                1 => Some(LATTE.blue),
-                                                2 => Some(LATTE.green),
-                                                3 => Some(LATTE.mauve),
-                                                4 => Some(LATTE.maroon),
-                                                5 => Some(LATTE.sapphire),
-                                                6 => Some(LATTE.flamingo),
-                                                7 => Some(LATTE.lavender),
-                                                _ => Some(LATTE.text),
-                                            };
-                                
-                                    //if it is selected
-                                    //disable the widget (button)
-                                    enable = false;
-
-                                    if val > 0 && val < 9 {
-                                        //if it's not a bomb and it has a value
-                                        //show it
-                                        lbl = format!("{}", val);
-                                    } else if val == 9 {
-                                        //it's a bomb
-                                        lbl = String::from_utf8(UTF8_BOMB.to_vec()).unwrap();
-                                    }
-                                } else if self.map[ind].flag == CellTag::DEAD {
-                                   
-                                    ui.visuals_mut().override_text_color = 
-                                        Some(LATTE.red);
-
-                                    lbl = String::from_utf8(UTF8_DEAD.to_vec()).unwrap();
-                                }
+                //endless board widgets
+                if let Some(endless) = &mut self.endless {
+                    self.cell_size = endless.ui(ui, self.cell_size, &self.look, self.skin.as_ref(), !self.welcome);
+                    return;
+                }
+
+                if self.fit {
+                    //leave some room for the scroll bars
+                    let available = ui.available_size() - egui::Vec2::splat(12.);
+                    self.cell_size = self.fit_cell_size(available);
+                    self.fit = false;
+                }
 
-                                let button = egui::Button::new(lbl);
-                                                                  
-                                ui.add_enabled_ui(enable, |ui| {
-
-                                    let response = ui.add_sized(
-                                        [CELL_SIZE, CELL_SIZE], 
-                                        button);
-                                    
-                                    if response.clicked() {
-                                        if self.map[ind].flag == CellTag::FLAGGED {
-                                            self.f_mines -= 1;
-                                        }
-                                        
-                                        self.set_selected(ind);
-                                        
-                                        if self.map[ind].val == 0 {
-                                            self.clean_neighbour_cells(r, c);
-                                        }
-                                        
-                                        if self.map[ind].val == 9 {
-                                            self.state = 2; //looser
-                                            self.map[ind].flag = CellTag::DEAD;
-                                            self.check_looser_map();
-                                        } else if (self.selected + self.f_mines) >= self.map.len() {
-                                            self.state = 1; //winner
-                                        }
-                                    };
-
-                                    if response.secondary_clicked() {
-                                        
-                                        if self.map[ind].flag == CellTag::FLAGGED {
-                                            self.map[ind].flag = CellTag::UNSELECTED;
-                                            self.f_mines -= 1;
-                                        } else if self.map[ind].flag == CellTag::UNSELECTED
-                                                && self.f_mines < self.n_mines {
-                                            self.map[ind].flag = CellTag::FLAGGED;
-                                            self.f_mines += 1;
-                                        }
-
-                                        if (self.selected + self.f_mines) >= self.map.len() {
-                                            self.state = 1; //winner
-                                        }
-                                    }
-                                });
-                            }//for columns
-                        });//horizontal
-                    }//for rows
+                //map widget
+                egui::ScrollArea::both().show_viewport(ui, |ui, viewport| {
+                    self.board_ui(ui, viewport);
                 });//ScrollArea
             });//CentralPanel
+
+        //the achievements follow the moves of the frame, puzzles aren't games
+        let events: Vec<Event> = self.game.events.drain(..).collect();
+        if self.puzzles.is_none() {
+            self.achievements.handle(&events, &self.game, self.clock.elapsed().as_secs_f64());
+        }
+
+        //finished games go to the results, an undone loss
+        //can finish again later, a redone one is already there
+        if events.contains(&Event::Start) {
+            self.recorded.clear();
+        }
+        let position = self.game.history.position();
+        if let Some(endless) = &mut self.endless {
+            //the normal board behind the run isn't played
+            if endless.lost && !endless.recorded {
+                self.results.add_endless(EndlessRun {
+                    density: endless.density,
+                    cleared: endless.cleared,
+                    time: self.clock.elapsed().as_secs_f64(),
+                });
+                endless.recorded = true;
+            }
+        } else if self.game.state != 0 && !self.recorded.contains(&position) {
+            //puzzles only keep their stars
+            if let Some(puzzles) = &self.puzzles {
+                self.results.add_puzzle(&puzzles.name(), puzzles.attempt.stars.unwrap_or_default());
+            } else {
+                self.results.add(GameResult {
+                    board: self.board.clone(),
+                    won: self.game.state == 1,
+                    time: self.clock.elapsed().as_secs_f64(),
+                    undo: self.game.history.used,
+                });
+            }
+            if let Some((date, true)) = &self.daily {
+                self.results.finish_daily(date, self.game.state == 1, self.clock.elapsed().as_secs_f64());
+            }
+            if let Some(level) = self.level.map(|index| &self.campaign[index]) {
+                let time = self.clock.elapsed().as_secs_f64();
+                let rate = self.game.bbbv() as f64 / time.max(0.001);
+                let passed = level.passed(&self.game, time);
+                if passed {
+                    self.results.campaign.insert(level.name.clone());
+                }
+                self.level_msg = Some(format!("{} {}: {:.1}s, {:.2} 3BV/s", level.name,
+                    if passed { "passed" } else { "not passed" }, time, rate));
+            }
+            self.recorded.push(position);
+        }
+
+        if self.results_window {
+            self.results_window = self.results.ui(ctx);
+        }
+
+        if self.achievements_window {
+            self.achievements_window = self.achievements.ui(ctx, &self.look);
+        }
+        self.achievements.toasts(ctx, &self.look);
+
+        if let Some(puzzles) = &mut self.puzzles {
+            let (open, start) = puzzles.ui(ctx, &self.look, &self.results.puzzles);
+            if start.is_some() {
+                self.new_puzzle(start);
+            }
+            if !open {
+                self.puzzles = None;
+            }
+        }
+
+        if let Some(editor) = &mut self.editor {
+            let (open, play) = editor.window(ctx, &self.look);
+            if play {
+                self.playtest();
+            }
+            if !open {
+                self.editor = None;
+            }
+        }
+
+        //the race lines arrive between frames
+        if let Some(race) = &mut self.race {
+            if race.racing() && self.game.state != 0 {
+                race.finish(self.game.state == 1);
+            }
+            if let Some(game) = race.poll() {
+                self.game = game;
+                self.start_lan_game("race");
+            }
+
+            let can_start = self.s_shape != Shape::Bitmap;
+            let Some(race) = &mut self.race else { return };
+            let (open, start) = race.ui(ctx, &self.look, can_start);
+            if start {
+                self.game = race.start(self.s_rows, self.s_columns, self.s_layers,
+                    self.n_mines, self.max_mines, self.s_shape);
+                self.start_lan_game("race");
+            }
+            if !open {
+                self.leave_race_game();
+                self.race = None;
+            }
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        if let Some(coop) = &mut self.coop {
+            if coop.poll(&mut self.game) {
+                self.start_lan_game("co-op");
+            }
+
+            let Some(coop) = &mut self.coop else { return };
+            let pointed = if self.show_cursor { Some(self.cursor) } else { self.hovered };
+            coop.point(pointed.filter(|_| !self.welcome && self.game.state == 0));
+
+            let can_start = self.s_shape != Shape::Bitmap;
+            let (open, start) = coop.ui(ctx, &self.look, &self.game, can_start);
+            if start {
+                //settings that don't make a board show in the window
+                if let Some(game) = coop.start(self.s_rows, self.s_columns, self.s_layers,
+                    self.n_mines, self.max_mines, self.s_shape) {
+                    self.game = game;
+                    self.start_lan_game("co-op");
+                }
+            }
+            if !open {
+                self.coop = None;
+            }
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
     }
 }
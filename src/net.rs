@@ -0,0 +1,198 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+//address the host listens on by default
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+//the host is always peer 0
+pub const HOST: usize = 0;
+
+//a peer that doesn't read its lines for this long is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+//what happened on a link
+pub enum Event {
+    Line(usize, String),
+    Closed(usize),
+}
+
+//what the network threads send
+enum Raw {
+    Joined(Peer),
+    Line(usize, String),
+    Closed(usize),
+}
+
+//a connection, its lines are written by its own thread so a peer
+//that stops reading can't block the game
+struct Peer {
+    id: usize,
+    lines: Sender<Option<String>>, //lines for the writing thread, None closes
+}
+
+impl Peer {
+    fn new(id: usize, mut writer: TcpStream) -> std::io::Result<Self> {
+        writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let (lines, receiver) = channel::<Option<String>>();
+        std::thread::spawn(move || {
+            while let Ok(Some(line)) = receiver.recv() {
+                if writeln!(writer, "{}", line).is_err() {
+                    break;
+                }
+            }
+            //closed, dropped or stuck: the reading thread sees the connection close
+            let _ = writer.shutdown(std::net::Shutdown::Both);
+        });
+        Ok(Self { id, lines })
+    }
+}
+
+//tcp connections of a host with its clients or of a client with its host,
+//exchanging lines of text
+pub struct Link {
+    events: Receiver<Raw>,
+    peers: Vec<Peer>, //the clients of the host, the host of a client
+    closed: Arc<AtomicBool>, //stops the host listening when the link is dropped
+}
+
+// send the lines read from stream as events of the connection id
+fn read_lines(id: usize, stream: TcpStream, events: Sender<Raw>) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if events.send(Raw::Line(id, line)).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(Raw::Closed(id));
+    });
+}
+
+impl Link {
+    // listen on addr, clients get the ids after the host
+    pub fn host(addr: &str) -> Result<Self, String> {
+        let listener = TcpListener::bind(addr).map_err(|e| format!("{}: {}", addr, e))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let (sender, events) = channel();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let listening = closed.clone();
+        std::thread::spawn(move || {
+            let mut id = HOST;
+            while !listening.load(Ordering::Relaxed) {
+                let Ok((stream, _)) = listener.accept() else {
+                    std::thread::sleep(Duration::from_millis(100));
+                    continue;
+                };
+                let Ok(reader) = stream.set_nonblocking(false).and_then(|_| stream.try_clone()) else {
+                    continue;
+                };
+                let Ok(peer) = Peer::new(id + 1, stream) else { continue };
+                id += 1;
+                if sender.send(Raw::Joined(peer)).is_err() {
+                    return;
+                }
+                read_lines(id, reader, sender.clone());
+            }
+        });
+
+        Ok(Self { events, peers: Vec::new(), closed })
+    }
+
+    // connect to the host on addr, saying hello
+    pub fn join(addr: &str, hello: &str) -> Result<Self, String> {
+        let socket = addr.to_socket_addrs().ok()
+            .and_then(|mut a| a.next())
+            .ok_or(format!("{} isn't an address", addr))?;
+        let mut stream = TcpStream::connect_timeout(&socket, Duration::from_secs(3))
+            .map_err(|e| format!("{}: {}", addr, e))?;
+        writeln!(stream, "{}", hello).map_err(|e| e.to_string())?;
+
+        let (sender, events) = channel();
+        read_lines(HOST, stream.try_clone().map_err(|e| e.to_string())?, sender);
+
+        Ok(Self {
+            events,
+            peers: vec![Peer::new(HOST, stream).map_err(|e| e.to_string())?],
+            closed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    // send a line to the peers, but the one it came from
+    pub fn send(&mut self, line: &str, except: Option<usize>) {
+        //a peer that can't be written to is closing
+        for peer in self.peers.iter().filter(|p| Some(p.id) != except) {
+            let _ = peer.lines.send(Some(line.to_string()));
+        }
+    }
+
+    pub fn send_to(&mut self, to: usize, line: &str) {
+        if let Some(peer) = self.peers.iter().find(|p| p.id == to) {
+            let _ = peer.lines.send(Some(line.to_string()));
+        }
+    }
+
+    // stop talking to a peer, after the lines sent to it
+    pub fn drop_peer(&mut self, id: usize) {
+        if let Some(peer) = self.peers.iter().find(|p| p.id == id) {
+            let _ = peer.lines.send(None);
+        }
+        self.peers.retain(|p| p.id != id);
+    }
+
+    // the next event, None until something happens
+    // new clients are kept as peers, they say hello next
+    pub fn poll(&mut self) -> Option<Event> {
+        loop {
+            match self.events.try_recv().ok()? {
+                Raw::Joined(peer) => self.peers.push(peer),
+                Raw::Line(id, line) => return Some(Event::Line(id, line)),
+                Raw::Closed(id) => {
+                    self.peers.retain(|p| p.id != id);
+                    return Some(Event::Closed(id));
+                },
+            }
+        }
+    }
+}
+
+//dropping the link closes the connections,
+//the writing threads close them once their lines are written
+impl Drop for Link {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn a_peer_that_doesnt_read_doesnt_block() {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = format!("127.0.0.1:{}", port);
+        let mut link = Link::host(&addr).unwrap();
+        let _client = TcpStream::connect(&addr).unwrap();
+
+        let start = Instant::now();
+        while link.peers.is_empty() && start.elapsed() < Duration::from_secs(5) {
+            link.poll();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(link.peers.len(), 1);
+
+        //far more than the socket buffers hold
+        let line = "x".repeat(1000);
+        let start = Instant::now();
+        for _ in 0..20_000 {
+            link.send(&line, None);
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
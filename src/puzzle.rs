@@ -0,0 +1,240 @@
+//puzzles: positions with revealed cells to solve by deduction
+//
+//a pack is a toml file with a name and its puzzles:
+//  name = "Patterns"
+//  [[puzzle]]
+//  name = "1-2-1"
+//  goal = "safe"          # reveal a cell the numbers prove free
+//  hint = "..."           # optional
+//  board = """
+//  *.*
+//  ooo
+//  """
+//the boards are board files, see engine::parse_board
+//with goal = "flag" every mine of the board has to be flagged,
+//the revealed cells tell where they are
+//a puzzle scores up to 3 stars: a safe cell that wasn't proven free is a lucky
+//guess worth a star, each reveal and each flag on a free cell costs a star
+//of a flag puzzle, a mine ends the puzzle with none
+use serde::Deserialize;
+
+use crate::engine::{parse_board, CellTag, Game, Move};
+use crate::solver::{deduce, Deduction};
+
+//stars of a puzzle solved without a mistake
+pub const MAX_STARS: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    Safe, //reveal a free cell
+    Flag, //flag all the mines
+}
+
+impl Goal {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Goal::Safe => "Find a cell without a mine",
+            Goal::Flag => "Flag all the mines",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: Goal,
+    #[serde(default)]
+    pub hint: Option<String>,
+    pub board: String, //board file text
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pack {
+    pub name: String,
+    #[serde(rename = "puzzle", default)]
+    pub puzzles: Vec<Puzzle>,
+}
+
+// parse the text of a puzzle pack, every puzzle has to be solvable
+pub fn parse_pack(text: &str) -> Result<Pack, String> {
+    let pack: Pack = toml::from_str(text).map_err(|e| e.to_string())?;
+    if pack.puzzles.is_empty() {
+        return Err(String::from("the pack has no puzzle"));
+    }
+
+    for puzzle in &pack.puzzles {
+        puzzle.game().map_err(|e| format!("puzzle '{}': {}", puzzle.name, e))?;
+    }
+    Ok(pack)
+}
+
+impl Puzzle {
+    // the starting position, checked against the goal
+    pub fn game(&self) -> Result<Game, String> {
+        let game = parse_board(&self.board)?;
+        if game.selected == 0 || game.state != 0 {
+            return Err(String::from("the board has no revealed cell to start from"));
+        }
+
+        let proven = deduce(&game);
+        let mined = game.map.iter().filter(|cell| cell.mines > 0).count();
+        match self.goal {
+            Goal::Safe if proven.safe.is_empty() =>
+                Err(String::from("no cell is proven free, it needs a guess")),
+            Goal::Flag if proven.mines.len() < mined =>
+                Err(format!("only {} of the {} mined cells can be told", proven.mines.len(), mined)),
+            _ => Ok(game),
+        }
+    }
+}
+
+//a try at a puzzle, the moves on its game go through play
+pub struct Attempt {
+    pub goal: Goal,
+    proven: Deduction, //what the start tells
+    pub reveals: usize, //cells revealed in a flag puzzle
+    pub mistakes: usize, //flags put on free cells
+    pub stars: Option<u8>, //the score once the puzzle is over
+}
+
+impl Attempt {
+    pub fn new(puzzle: &Puzzle, game: &Game) -> Self {
+        Self { goal: puzzle.goal, proven: deduce(game), reveals: 0, mistakes: 0, stars: None }
+    }
+
+    // play a move, the game is won or lost when the puzzle is over
+    pub fn play(&mut self, game: &mut Game, mv: Move) {
+        if self.stars.is_some() || game.state != 0 || mv.cell() >= game.map.len() {
+            return;
+        }
+
+        let cell = game.map[mv.cell()];
+        match (self.goal, mv) {
+            //the first reveal ends a safe puzzle
+            (Goal::Safe, Move::Reveal(ind)) if cell.flag == CellTag::UNSELECTED => {
+                game.apply(mv);
+                self.stars = Some(match game.state {
+                    2 => 0,
+                    _ if self.proven.safe.contains(&ind) => MAX_STARS,
+                    _ => 1,
+                });
+            },
+            (Goal::Safe, Move::Reveal(_) | Move::Chord(_)) => {},
+            (Goal::Flag, Move::Reveal(_) | Move::Chord(_)) => {
+                let selected = game.selected;
+                game.apply(mv);
+                if game.state == 2 {
+                    self.stars = Some(0);
+                } else if game.selected > selected {
+                    self.reveals += 1;
+                }
+            },
+            (Goal::Flag, Move::Flag(..)) => {
+                if cell.mines == 0 && cell.flag == CellTag::UNSELECTED {
+                    self.mistakes += 1;
+                }
+                game.apply(mv);
+            },
+            _ => game.apply(mv),
+        }
+
+        //a flag puzzle is over with the right flags on every mine
+        let flagged = game.map.iter().filter(|cell| cell.mines > 0)
+            .all(|cell| cell.flag == CellTag::FLAGGED && cell.flags == cell.mines);
+        if self.goal == Goal::Flag && self.stars.is_none() && flagged {
+            let lost = (self.reveals + self.mistakes).min(usize::from(MAX_STARS - 1));
+            self.stars = Some(MAX_STARS - lost as u8);
+        }
+
+        match self.stars {
+            Some(0) => game.state = 2, //looser
+            Some(_) => game.state = 1, //winner
+            None => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //1-2-1 on the left, a 50/50 on the right
+    const SAFE: &str = "*.*-.*\nooo-oo\nooo-oo\n";
+    //1-2-1 between holes, the mines at 1 and 3
+    const FLAG: &str = "-*.*-\n-ooo-\n-ooo-\n";
+
+    fn start(goal: Goal, board: &str) -> (Game, Attempt) {
+        let puzzle = Puzzle { name: String::from("test"), goal, hint: None, board: board.to_string() };
+        let game = puzzle.game().expect("a valid puzzle");
+        let attempt = Attempt::new(&puzzle, &game);
+        (game, attempt)
+    }
+
+    fn play(goal: Goal, board: &str, moves: &[Move]) -> (Game, Attempt) {
+        let (mut game, mut attempt) = start(goal, board);
+        for &mv in moves {
+            attempt.play(&mut game, mv);
+        }
+        (game, attempt)
+    }
+
+    #[test]
+    fn proven_reveal() {
+        let (game, attempt) = play(Goal::Safe, SAFE, &[Move::Reveal(1)]);
+        assert_eq!((attempt.stars, game.state), (Some(MAX_STARS), 1));
+    }
+
+    #[test]
+    fn lucky_reveal() {
+        let (game, attempt) = play(Goal::Safe, SAFE, &[Move::Reveal(4)]);
+        assert_eq!((attempt.stars, game.state), (Some(1), 1));
+    }
+
+    #[test]
+    fn mine() {
+        let (game, attempt) = play(Goal::Safe, SAFE, &[Move::Reveal(5)]);
+        assert_eq!((attempt.stars, game.state), (Some(0), 2));
+
+        let (game, attempt) = play(Goal::Flag, FLAG, &[Move::Flag(1, false), Move::Reveal(3)]);
+        assert_eq!((attempt.stars, game.state), (Some(0), 2));
+    }
+
+    #[test]
+    fn flag_stars() {
+        let flags = [Move::Flag(1, false), Move::Flag(3, false)];
+        let (game, attempt) = play(Goal::Flag, FLAG, &flags);
+        assert_eq!((attempt.stars, game.state), (Some(MAX_STARS), 1));
+
+        //a reveal costs a star
+        let (_, attempt) = play(Goal::Flag, FLAG, &[&[Move::Reveal(2)], &flags[..]].concat());
+        assert_eq!((attempt.reveals, attempt.stars), (1, Some(2)));
+
+        //so does a flag on a free cell, taking it back doesn't help
+        let wrong = [Move::Flag(2, false), Move::Unflag(2)];
+        let (_, attempt) = play(Goal::Flag, FLAG, &[&wrong[..], &flags[..]].concat());
+        assert_eq!((attempt.mistakes, attempt.stars), (1, Some(2)));
+
+        //a solved puzzle keeps a star
+        let (game, attempt) = play(Goal::Flag, FLAG, &[&wrong[..], &wrong[..], &[Move::Reveal(2)], &flags[..]].concat());
+        assert_eq!((attempt.mistakes, attempt.reveals), (2, 1));
+        assert_eq!((attempt.stars, game.state), (Some(1), 1));
+    }
+
+    #[test]
+    fn over_after_the_stars() {
+        let (mut game, mut attempt) = play(Goal::Safe, SAFE, &[Move::Reveal(1)]);
+        let before = game.clone();
+        attempt.play(&mut game, Move::Reveal(5));
+        attempt.play(&mut game, Move::Flag(0, false));
+        assert_eq!(attempt.stars, Some(MAX_STARS));
+        assert_eq!((game.state, game.selected, game.f_mines), (before.state, before.selected, before.f_mines));
+        assert!(game.map == before.map);
+
+        //even if the game were playable again
+        game.state = 0;
+        attempt.play(&mut game, Move::Reveal(5));
+        assert_eq!((attempt.stars, game.state), (Some(MAX_STARS), 0));
+        assert!(game.map == before.map);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use eframe::egui;
+
+use rustymines::engine::Game;
+use rustymines::puzzle::{parse_pack, Attempt, Pack, MAX_STARS};
+
+use crate::theme::Look;
+
+//puzzle pack built in the game
+const PATTERNS: &str = include_str!("../assets/patterns.toml");
+
+//puzzle mode: the puzzles of a pack one after the other
+pub struct Puzzles {
+    pub pack: Pack,
+    pub current: usize, //puzzle in play
+    pub attempt: Attempt, //the try at the puzzle in play
+    path: String, //pack file to load
+    error: Option<String>, //why the pack file didn't load
+    hint: bool, //show the hint of the puzzle in play
+}
+
+// "2 of 3 stars"
+fn stars(n: u8) -> String {
+    format!("{} of {} stars", n, MAX_STARS)
+}
+
+impl Puzzles {
+    // the built-in pack and its first puzzle
+    pub fn new() -> (Self, Game) {
+        let pack = parse_pack(PATTERNS).expect("a valid built-in pack");
+        let game = pack.puzzles[0].game().expect("a valid built-in puzzle");
+        let this = Self {
+            attempt: Attempt::new(&pack.puzzles[0], &game),
+            pack,
+            current: 0,
+            path: String::new(),
+            error: None,
+            hint: false,
+        };
+        (this, game)
+    }
+
+    // start a puzzle of the pack, the pack checked they all load
+    pub fn start(&mut self, index: usize) -> Game {
+        let puzzle = &self.pack.puzzles[index];
+        let game = puzzle.game().expect("a puzzle of a checked pack");
+        self.attempt = Attempt::new(puzzle, &game);
+        self.current = index;
+        self.hint = false;
+        game
+    }
+
+    // name of the puzzle in play, for the results
+    pub fn name(&self) -> String {
+        format!("{}: {}", self.pack.name, self.pack.puzzles[self.current].name)
+    }
+
+    // pack and puzzle window, best has the best stars by puzzle name
+    // returns (open, puzzle to start)
+    pub fn ui(&mut self, ctx: &egui::Context, look: &Look, best: &BTreeMap<String, u8>) -> (bool, Option<usize>) {
+        let mut open = true;
+        let mut start = None;
+        egui::Window::new("Puzzles")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                let puzzle = &self.pack.puzzles[self.current];
+                ui.heading(&puzzle.name);
+                ui.label(puzzle.goal.text());
+                match self.attempt.stars {
+                    Some(0) => { ui.colored_label(look.theme.red, "A mine! Try again."); },
+                    Some(n) => { ui.colored_label(look.theme.green, format!("Solved, {}", stars(n))); },
+                    None => {},
+                }
+
+                if let Some(hint) = &puzzle.hint {
+                    if self.hint {
+                        ui.label(hint);
+                    } else if ui.button("Hint").clicked() {
+                        self.hint = true;
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    let last = self.pack.puzzles.len() - 1;
+                    if ui.add_enabled(self.current > 0, egui::Button::new("Previous")).clicked() {
+                        start = Some(self.current - 1);
+                    }
+                    if ui.button("Retry").clicked() {
+                        start = Some(self.current);
+                    }
+                    if ui.add_enabled(self.current < last, egui::Button::new("Next")).clicked() {
+                        start = Some(self.current + 1);
+                    }
+                });
+
+                ui.separator();
+                ui.label(format!("{}:", self.pack.name));
+                egui::ScrollArea::vertical().max_height(200.).show(ui, |ui| {
+                    egui::Grid::new("puzzles").striped(true).show(ui, |ui| {
+                        for (index, puzzle) in self.pack.puzzles.iter().enumerate() {
+                            if ui.selectable_label(index == self.current, &puzzle.name).clicked() {
+                                start = Some(index);
+                            }
+                            let key = format!("{}: {}", self.pack.name, puzzle.name);
+                            ui.label(best.get(&key).map_or(String::new(), |&n| stars(n)));
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                //other packs from a file
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        egui::Vec2::new(200., 24.),
+                        egui::TextEdit::singleline(&mut self.path)
+                            .hint_text("pack.toml"));
+
+                    if ui.button("Load").clicked() {
+                        let pack = std::fs::read_to_string(self.path.trim())
+                            .map_err(|e| format!("can't read {}: {}", self.path.trim(), e))
+                            .and_then(|text| parse_pack(&text)
+                                .map_err(|e| format!("{}: {}", self.path.trim(), e)));
+                        match pack {
+                            Ok(pack) => {
+                                self.pack = pack;
+                                self.error = None;
+                                start = Some(0);
+                            },
+                            Err(e) => self.error = Some(e),
+                        }
+                    }
+                });
+
+                if let Some(e) = &self.error {
+                    ui.colored_label(look.theme.red, e);
+                }
+            });
+
+        (open, start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_pack_loads() {
+        let pack = parse_pack(PATTERNS).expect("a valid built-in pack");
+        assert!(!pack.puzzles.is_empty());
+        for puzzle in &pack.puzzles {
+            assert!(puzzle.game().is_ok(), "{}", puzzle.name);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//python bindings of the learning environment, built with maturin
+//and the features in pyproject.toml:
+//  maturin develop
+//observations are flat lists, see the shape of the environment
+//the pyo3 macros convert results clippy sees as the same type
+#![allow(clippy::useless_conversion)]
+use pyo3::prelude::*;
+
+use crate::env::{self, Config, Rewards};
+
+// config from the keyword arguments, the defaults from Config
+fn config(rows: usize, columns: usize, mines: usize, flags: bool, rewards: Option<[f32; 6]>) -> Config {
+    let rewards = rewards.map_or(Rewards::default(), |[win, loss, progress, guess, flag, invalid]| {
+        Rewards { win, loss, progress, guess, flag, invalid }
+    });
+    Config { rows, columns, n_mines: mines, flags, rewards }
+}
+
+//a game played by an agent
+#[pyclass]
+struct Env(env::Env);
+
+#[pymethods]
+impl Env {
+    // rewards are (win, loss, progress, guess, flag, invalid)
+    #[new]
+    #[pyo3(signature = (rows=9, columns=9, mines=10, flags=false, rewards=None))]
+    fn new(rows: usize, columns: usize, mines: usize, flags: bool, rewards: Option<[f32; 6]>) -> Self {
+        Self(env::Env::new(config(rows, columns, mines, flags, rewards)))
+    }
+
+    #[getter]
+    fn shape(&self) -> [usize; 3] {
+        self.0.shape()
+    }
+
+    #[getter]
+    fn actions(&self) -> usize {
+        self.0.actions()
+    }
+
+    #[pyo3(signature = (seed=0))]
+    fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.0.reset(seed)
+    }
+
+    fn step(&mut self, action: usize) -> (Vec<f32>, f32, bool) {
+        self.0.step(action)
+    }
+
+    fn valid_actions(&self) -> Vec<bool> {
+        self.0.valid_actions()
+    }
+
+    fn won(&self) -> bool {
+        self.0.won()
+    }
+}
+
+//a batch of environments stepped together
+#[pyclass]
+struct VecEnv(env::VecEnv);
+
+#[pymethods]
+impl VecEnv {
+    #[new]
+    #[pyo3(signature = (n, rows=9, columns=9, mines=10, flags=false, rewards=None, seed=0))]
+    fn new(n: usize, rows: usize, columns: usize, mines: usize, flags: bool,
+        rewards: Option<[f32; 6]>, seed: u64) -> Self {
+        Self(env::VecEnv::new(config(rows, columns, mines, flags, rewards), n, seed))
+    }
+
+    fn reset(&mut self) -> Vec<f32> {
+        self.0.reset()
+    }
+
+    // the batch steps without holding the interpreter
+    fn step(&mut self, py: Python<'_>, actions: Vec<usize>) -> PyResult<(Vec<f32>, Vec<f32>, Vec<bool>)> {
+        if actions.len() != self.0.envs.len() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                format!("{} actions for {} environments", actions.len(), self.0.envs.len())));
+        }
+        Ok(py.allow_threads(|| self.0.step(&actions)))
+    }
+}
+
+#[pymodule]
+fn rustymines(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("CHANNELS", env::CHANNELS)?;
+    module.add_class::<Env>()?;
+    module.add_class::<VecEnv>()?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use eframe::egui;
+use egui::{Color32, Rect, Vec2};
+
+use rustymines::engine::{get_mask, CellTag, Game, Move, Shape};
+
+use crate::net::{Event, Link, HOST};
+use crate::theme::Look;
+
+//side of the mini maps in pixels, for the largest boards
+const MINI_MAP: f32 = 120.;
+
+//a racer, its board is replayed from its moves
+pub struct Player {
+    pub name: String,
+    pub game: Option<Game>,
+    pub result: Option<(bool, f64)>, //won and seconds
+    pub left: bool, //the connection closed
+}
+
+impl Player {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), game: None, result: None, left: false }
+    }
+}
+
+//a race over the lan, the host relays the lines of every player to the others
+//  client -> host  hello race <name>
+//  host -> client  welcome <id>
+//  host -> all     player <id> <name>, left <id>,
+//                  start <seed> <rows> <columns> <mines> <layers> <max mines> <shape>
+//  player -> all   move <id> <move>, done <id> <won> <seconds>
+pub struct Race {
+    pub host: bool,
+    pub me: Option<usize>, //my player id, once the host welcomed me
+    pub players: BTreeMap<usize, Player>,
+    pub started: Option<Instant>,
+    pub error: Option<String>,
+    link: Link,
+}
+
+impl Race {
+    // host a race on addr
+    pub fn host(addr: &str, name: &str) -> Result<Self, String> {
+        Ok(Self {
+            host: true,
+            me: Some(HOST),
+            players: BTreeMap::from([(HOST, Player::new(name))]),
+            started: None,
+            error: None,
+            link: Link::host(addr)?,
+        })
+    }
+
+    // join the race hosted on addr
+    pub fn join(addr: &str, name: &str) -> Result<Self, String> {
+        Ok(Self {
+            host: false,
+            me: None,
+            players: BTreeMap::new(),
+            started: None,
+            error: None,
+            link: Link::join(addr, &format!("hello race {}", name))?,
+        })
+    }
+
+    // my result, once my game is over
+    pub fn finished(&self) -> bool {
+        self.me.and_then(|me| self.players.get(&me)).is_some_and(|p| p.result.is_some())
+    }
+
+    // racing and my game isn't over
+    pub fn racing(&self) -> bool {
+        self.started.is_some() && !self.finished()
+    }
+
+    // start the race for everyone, only the host can
+    // bitmap shapes can't be sent, the host checks it
+    pub fn start(&mut self, rows: usize, columns: usize, layers: usize,
+        n_mines: usize, max_mines: u8, shape: Shape) -> Game {
+        let seed: u64 = rand::random();
+        let line = format!("start {} {} {} {} {} {} {}",
+            seed, rows, columns, n_mines, layers, max_mines, shape.name());
+        self.link.send(&line, None);
+        self.receive(&line).expect("a valid start")
+    }
+
+    // send my move to the other players
+    pub fn send_move(&mut self, mv: Move) {
+        if let (true, Some(me)) = (self.racing(), self.me) {
+            let line = format!("move {} {}", me, mv.text());
+            self.link.send(&line, None);
+            self.receive(&line);
+        }
+    }
+
+    // tell the others my game is over
+    pub fn finish(&mut self, won: bool) {
+        if let (true, Some(me), Some(start)) = (self.racing(), self.me, self.started) {
+            let line = format!("done {} {} {:.3}", me, won, start.elapsed().as_secs_f64());
+            self.link.send(&line, None);
+            self.receive(&line);
+        }
+    }
+
+    // apply a line from a player, a start gives the game to play
+    fn receive(&mut self, line: &str) -> Option<Game> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut words = rest.split_whitespace();
+        let id: Option<usize> = rest.split_whitespace().next().and_then(|w| w.parse().ok());
+
+        match command {
+            "welcome" => self.me = id,
+            "player" => {
+                let name = rest.split_once(' ').map(|(_, n)| n).unwrap_or_default();
+                self.players.insert(id?, Player::new(name));
+            },
+            "left" => self.players.get_mut(&id?)?.left = true,
+            "start" => {
+                let words: Vec<&str> = words.collect();
+                let [ref numbers @ .., shape] = words[..] else { return None };
+                let numbers: Vec<u64> = numbers.iter().filter_map(|w| w.parse().ok()).collect();
+                let [seed, rows, columns, n_mines, layers, max_mines] = numbers[..] else { return None };
+                let (rows, columns, layers) = (rows as usize, columns as usize, layers as usize);
+                if !(4..=500).contains(&rows) || !(4..=500).contains(&columns)
+                    || !(1..=5).contains(&layers) || !(1..=4).contains(&max_mines) {
+                    return None;
+                }
+                let shape = Shape::ALL.into_iter().find(|s| s.name() == shape && *s != Shape::Bitmap)?;
+                let game = Game::new(rows, columns, layers, n_mines as usize, max_mines as u8,
+                    &get_mask(shape, rows, columns), seed);
+                for player in self.players.values_mut() {
+                    player.game = Some(game.clone());
+                    player.result = None;
+                }
+                self.started = Some(Instant::now());
+                return Some(game);
+            },
+            "move" => {
+                let mv = Move::parse(rest.split_once(' ')?.1)?;
+                let game = self.players.get_mut(&id?)?.game.as_mut()?;
+                //a bad move from the network must not panic
+                if mv.cell() < game.map.len() {
+                    game.apply(mv);
+                }
+            },
+            "done" => {
+                words.next();
+                let won = words.next()?.parse().ok()?;
+                let time = words.next()?.parse().ok()?;
+                self.players.get_mut(&id?)?.result = Some((won, time));
+            },
+            "error" => self.error = Some(rest.to_string()),
+            _ => {},
+        }
+        None
+    }
+
+    // handle the network events, a start gives the game to play
+    pub fn poll(&mut self) -> Option<Game> {
+        let mut start = None;
+        while let Some(event) = self.link.poll() {
+            match event {
+                Event::Line(id, line) if self.host => {
+                    if let Some(name) = line.strip_prefix("hello race ") {
+                        self.hello(id, name);
+                        continue;
+                    }
+                    if line.starts_with("hello ") {
+                        self.link.send_to(id, "error the host plays a race, not co-op");
+                        self.link.drop_peer(id);
+                        continue;
+                    }
+
+                    //players only speak for themselves
+                    let from = line.split_whitespace().nth(1).and_then(|w| w.parse().ok());
+                    if from == Some(id) && (line.starts_with("move ") || line.starts_with("done ")) {
+                        self.link.send(&line, Some(id));
+                        self.receive(&line);
+                    }
+                },
+                Event::Line(_, line) => start = self.receive(&line).or(start),
+                Event::Closed(id) if self.host => {
+                    if self.players.contains_key(&id) {
+                        let line = format!("left {}", id);
+                        self.link.send(&line, None);
+                        self.receive(&line);
+                    }
+                },
+                Event::Closed(_) => {
+                    self.error.get_or_insert(String::from("the host left the race"));
+                },
+            }
+        }
+        start
+    }
+
+    // a new player, the race waits in the lobby
+    fn hello(&mut self, id: usize, name: &str) {
+        if self.started.is_some() {
+            self.link.send_to(id, "error the race started already");
+            self.link.drop_peer(id);
+            return;
+        }
+        self.link.send_to(id, &format!("welcome {}", id));
+
+        self.players.insert(id, Player::new(name.trim()));
+        let lines: Vec<String> = self.players.iter()
+            .map(|(id, p)| format!("player {} {}", id, p.name))
+            .collect();
+        for line in lines {
+            self.link.send(&line, None);
+        }
+    }
+
+    // lobby, progress and results window
+    // returns (open, start), closing it leaves the race
+    pub fn ui(&mut self, ctx: &egui::Context, look: &Look, can_start: bool) -> (bool, bool) {
+        let mut open = true;
+        let mut start = false;
+        egui::Window::new("Race")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(e) = &self.error {
+                    ui.colored_label(look.theme.red, e);
+                }
+
+                if self.started.is_none() {
+                    if self.host {
+                        ui.label("Waiting for players, they join the race with your address.");
+                        if ui.add_enabled(can_start && self.players.len() > 1,
+                            egui::Button::new("Start with the welcome settings")).clicked() {
+                            start = true;
+                        }
+                    } else {
+                        ui.label(if self.me.is_some() { "Waiting for the host to start." } else { "Connecting..." });
+                    }
+                }
+
+                //the finished players first, winners by time
+                let mut players: Vec<(&usize, &Player)> = self.players.iter().collect();
+                players.sort_by(|(_, a), (_, b)| {
+                    let key = |p: &Player| match p.result {
+                        Some((true, time)) => (0, time),
+                        Some((false, time)) => (1, time),
+                        None => (2, 0.),
+                    };
+                    key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal)
+                });
+
+                egui::Grid::new("racers").striped(true).show(ui, |ui| {
+                    for (id, player) in players {
+                        let you = if Some(*id) == self.me { " (you)" } else { "" };
+                        ui.label(format!("{}{}", player.name, you));
+
+                        let status = match (player.result, &player.game) {
+                            _ if player.left => String::from("left"),
+                            (Some((true, time)), _) => format!("won in {:.1}s", time),
+                            (Some((false, time)), _) => format!("lost after {:.1}s", time),
+                            (None, Some(game)) => format!("{} of {} cells", game.selected, game.cells),
+                            (None, None) => String::from("ready"),
+                        };
+                        ui.label(status);
+
+                        if let Some(game) = &player.game {
+                            mini_map(ui, game, look);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+        (open, start)
+    }
+}
+
+// the first layer of the board of a player in at most MINI_MAP pixels,
+// a block per cell, or per step x step cells sampled on big boards
+pub fn mini_map(ui: &mut egui::Ui, game: &Game, look: &Look) {
+    let px = (MINI_MAP / game.columns.max(game.rows) as f32).min(6.);
+    let size = Vec2::new(game.columns as f32, game.rows as f32) * px;
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+    //blocks of at least a pixel
+    let step = (1. / px).ceil().max(1.) as usize;
+    let block = px * step as f32;
+    let painter = ui.painter_at(rect);
+    for (r, c) in (0..game.rows).step_by(step).flat_map(|r| (0..game.columns).step_by(step).map(move |c| (r, c))) {
+        let cell = &game.map[game.index(0, r, c)];
+        let color = match cell.flag {
+            CellTag::UNSELECTED => look.theme.overlay0,
+            CellTag::FLAGGED => look.theme.red,
+            CellTag::SELECTED if cell.mines > 0 => look.theme.black,
+            CellTag::SELECTED => look.theme.surface0,
+            CellTag::DEAD => look.theme.maroon,
+            CellTag::HOLE => Color32::TRANSPARENT,
+        };
+        let min = rect.min + Vec2::new(c as f32, r as f32) * px;
+        painter.rect_filled(Rect::from_min_size(min, Vec2::splat(block)), 0., color);
+    }
+}
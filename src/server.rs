@@ -0,0 +1,368 @@
+//local http api with json bodies, each game a session keyed by its id
+//
+//  POST   /games                  new game {rows, columns, mines, layers, max_mines,
+//                                 seed, preset, board}, all optional, board is the
+//                                 text of a board file
+//  GET    /games                  ids of the sessions
+//  GET    /games/<id>             visible state, cells as in the bot protocol
+//  POST   /games/<id>/actions     {action: reveal|flag|unflag|chord|resign, row, column, layer}
+//  GET    /games/<id>/result      result of a finished game
+//  GET    /games/<id>/replay      actions done, and the mines once the game is over
+//  DELETE /games/<id>
+//errors are answered with {"error": message}
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::bot::{self, Reply};
+use crate::engine::{check_mines, get_mask, parse_board, Game, Preset, Shape};
+
+//threads answering requests
+const THREADS: usize = 4;
+
+//settings of a new game
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NewGame {
+    rows: usize,
+    columns: usize,
+    mines: usize,
+    layers: usize,
+    max_mines: u8,
+    seed: Option<u64>, //random if not given
+    preset: Option<String>, //sets rows, columns and mines
+    board: Option<String>, //board file text, sets everything
+}
+
+impl Default for NewGame {
+    fn default() -> Self {
+        let (rows, columns, mines) = Preset::Intermediate.board();
+        Self { rows, columns, mines, layers: 1, max_mines: 1, seed: None, preset: None, board: None }
+    }
+}
+
+//a move posted to a game
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Action {
+    action: String,
+    #[serde(default)]
+    row: usize,
+    #[serde(default)]
+    column: usize,
+    #[serde(default)]
+    layer: usize,
+    #[serde(default, skip_deserializing)]
+    time: f64, //seconds since the game started
+}
+
+//a game played over the api
+struct Session {
+    game: Game,
+    start: Instant,
+    time: Option<f64>, //seconds the game took, once it is over
+    actions: Vec<Action>,
+}
+
+impl Session {
+    fn state(&self) -> &'static str {
+        match self.game.state {
+            0 => "playing",
+            1 => "won",
+            _ => "lost",
+        }
+    }
+
+    // visible state of the game
+    fn view(&self, id: u64) -> Value {
+        let game = &self.game;
+        let board: Vec<Vec<String>> = (0..game.map.len())
+            .step_by(game.columns)
+            .map(|start| (start..start + game.columns).map(|ind| bot::cell_text(game, ind)).collect())
+            .collect();
+
+        json!({
+            "id": id,
+            "rows": game.rows,
+            "columns": game.columns,
+            "layers": game.layers,
+            "mines": game.n_mines,
+            "flags": game.f_mines,
+            "seed": game.seed,
+            "state": self.state(),
+            "board": board,
+        })
+    }
+}
+
+//an http server with its sessions
+pub struct Server {
+    http: tiny_http::Server,
+    sessions: Mutex<BTreeMap<u64, Session>>,
+    next_id: Mutex<u64>,
+}
+
+// an error answer
+fn error(status: u16, message: impl std::fmt::Display) -> (u16, Value) {
+    (status, json!({ "error": message.to_string() }))
+}
+
+// a game from the settings of a new game
+fn new_game(settings: NewGame) -> Result<Game, String> {
+    if let Some(text) = &settings.board {
+        let game = parse_board(text)?;
+        if !(4..=500).contains(&game.rows) || !(4..=500).contains(&game.columns) {
+            return Err(String::from("board rows and columns must be between 4 and 500"));
+        }
+        if game.n_mines == 0 || game.state != 0 {
+            return Err(String::from("the board needs a mine and a covered free cell"));
+        }
+        return Ok(game);
+    }
+
+    let (rows, columns, mines) = match &settings.preset {
+        Some(name) => Preset::ALL.into_iter()
+            .find(|p| p.name() == name)
+            .ok_or(format!("unknown preset '{}', use beginner, intermediate or expert", name))?
+            .board(),
+        None => (settings.rows, settings.columns, settings.mines),
+    };
+
+    if !(4..=500).contains(&rows) || !(4..=500).contains(&columns) {
+        return Err(String::from("rows and columns must be between 4 and 500"));
+    }
+    if !(1..=5).contains(&settings.layers) || !(1..=4).contains(&settings.max_mines) {
+        return Err(String::from("layers must be between 1 and 5 and max_mines between 1 and 4"));
+    }
+    check_mines(mines, rows, columns, settings.layers, settings.max_mines)?;
+
+    Ok(Game::new(rows, columns, settings.layers, mines, settings.max_mines,
+        &get_mask(Shape::Rectangle, rows, columns),
+        settings.seed.unwrap_or_else(rand::random)))
+}
+
+impl Server {
+    // listen on addr, like "127.0.0.1:8080"
+    pub fn new(addr: &str) -> Result<Self, String> {
+        let http = tiny_http::Server::http(addr).map_err(|e| format!("{}: {}", addr, e))?;
+        Ok(Self { http, sessions: Mutex::new(BTreeMap::new()), next_id: Mutex::new(1) })
+    }
+
+    // address the server listens on
+    pub fn addr(&self) -> String {
+        self.http.server_addr().to_string()
+    }
+
+    // answer requests until the process ends
+    pub fn run(&self) {
+        std::thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    for mut request in self.http.incoming_requests() {
+                        let mut body = String::new();
+                        let (status, value) = match request.as_reader().read_to_string(&mut body) {
+                            Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+                            Err(e) => error(400, e),
+                        };
+
+                        let header = tiny_http::Header::from_bytes("Content-Type", "application/json")
+                            .expect("valid header");
+                        let response = tiny_http::Response::from_string(value.to_string())
+                            .with_status_code(status)
+                            .with_header(header);
+                        //the client may be gone already
+                        let _ = request.respond(response);
+                    }
+                });
+            }
+        });
+    }
+
+    // answer a request with a status and a json value
+    pub fn handle(&self, method: &str, url: &str, body: &str) -> (u16, Value) {
+        let path: Vec<&str> = url.split('?').next().unwrap_or_default()
+            .split('/')
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        let mut sessions = self.sessions.lock().expect("sessions lock");
+        match (method, &path[..]) {
+            ("POST", ["games"]) => {
+                let settings = match body.trim() {
+                    "" => NewGame::default(),
+                    body => match serde_json::from_str(body) {
+                        Ok(settings) => settings,
+                        Err(e) => return error(400, e),
+                    },
+                };
+                let game = match new_game(settings) {
+                    Ok(game) => game,
+                    Err(e) => return error(400, e),
+                };
+
+                let mut next_id = self.next_id.lock().expect("id lock");
+                let id = *next_id;
+                *next_id += 1;
+
+                let session = Session { game, start: Instant::now(), time: None, actions: Vec::new() };
+                let view = session.view(id);
+                sessions.insert(id, session);
+                (201, view)
+            },
+            ("GET", ["games"]) => (200, json!({ "games": sessions.keys().collect::<Vec<_>>() })),
+            (_, ["games", id, rest @ ..]) => {
+                let Some((id, session)) = id.parse().ok()
+                    .and_then(|id: u64| sessions.get_mut(&id).map(|s| (id, s))) else {
+                    return error(404, format!("no game {}", id));
+                };
+
+                match (method, rest) {
+                    ("GET", []) => (200, session.view(id)),
+                    ("DELETE", []) => {
+                        sessions.remove(&id);
+                        (200, json!({ "deleted": id }))
+                    },
+                    ("POST", ["actions"]) => {
+                        let mut action: Action = match serde_json::from_str(body) {
+                            Ok(action) => action,
+                            Err(e) => return error(400, e),
+                        };
+                        if session.game.state != 0 {
+                            return error(409, "the game is over");
+                        }
+
+                        //the same commands as the bot protocol
+                        let line = format!("{} {} {} {}", action.action, action.row, action.column, action.layer);
+                        let line = if action.action == "resign" { "resign" } else { &line };
+                        match bot::command(&mut session.game, line) {
+                            Ok(Reply::Move) => {},
+                            Ok(_) => return error(400, format!("unknown action '{}'", action.action)),
+                            Err(e) => return error(400, e),
+                        }
+
+                        action.time = session.start.elapsed().as_secs_f64();
+                        session.actions.push(action);
+                        if session.game.state != 0 {
+                            session.time = Some(session.start.elapsed().as_secs_f64());
+                        }
+                        (200, session.view(id))
+                    },
+                    ("GET", ["result"]) => match session.time {
+                        Some(time) => (200, json!({
+                            "id": id,
+                            "state": session.state(),
+                            "time": time,
+                            "moves": session.actions.len(),
+                            "seed": session.game.seed,
+                        })),
+                        None => error(409, "the game isn't over"),
+                    },
+                    ("GET", ["replay"]) => {
+                        let game = &session.game;
+                        //the mines are only shown once they can't help
+                        let mines: Option<Vec<Vec<u8>>> = (game.state != 0).then(|| game.map
+                            .chunks(game.columns)
+                            .map(|row| row.iter().map(|c| c.mines).collect())
+                            .collect());
+                        (200, json!({
+                            "id": id,
+                            "rows": game.rows,
+                            "columns": game.columns,
+                            "layers": game.layers,
+                            "seed": game.seed,
+                            "actions": session.actions,
+                            "mines": mines,
+                        }))
+                    },
+                    _ => error(404, format!("no {} {}", method, url)),
+                }
+            },
+            _ => error(404, format!("no {} {}", method, url)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "*...\n....\n....\n....\n";
+
+    fn server() -> Server {
+        Server::new("127.0.0.1:0").expect("a free port")
+    }
+
+    // post a board file, returns the game id
+    fn post_board(server: &Server) -> u64 {
+        let (status, view) = server.handle("POST", "/games", &json!({ "board": BOARD }).to_string());
+        assert_eq!(status, 201);
+        view["id"].as_u64().expect("an id")
+    }
+
+    fn act(server: &Server, id: u64, action: &str, row: usize, column: usize) -> (u16, Value) {
+        let body = json!({ "action": action, "row": row, "column": column }).to_string();
+        server.handle("POST", &format!("/games/{}/actions", id), &body)
+    }
+
+    #[test]
+    fn create_a_game() {
+        let server = server();
+        let (status, view) = server.handle("POST", "/games", r#"{"preset": "beginner", "seed": 7}"#);
+        assert_eq!(status, 201);
+        assert_eq!((view["rows"].as_u64(), view["mines"].as_u64()), (Some(9), Some(10)));
+        assert_eq!(view["state"], "playing");
+
+        let (status, list) = server.handle("GET", "/games", "");
+        assert_eq!((status, list["games"].clone()), (200, json!([view["id"]])));
+    }
+
+    #[test]
+    fn bad_settings_are_refused() {
+        let server = server();
+        for body in [r#"{"rows": 4, "columns": 4, "mines": 99}"#, r#"{"rows": 3}"#, r#"{"mines": 0}"#,
+            r#"{"board": "o"}"#, r#"{"board": "....\n....\n....\n....\n"}"#, r#"{"preset": "huge"}"#,
+            r#"{"colour": "red"}"#, "{"] {
+            assert_eq!(server.handle("POST", "/games", body).0, 400, "{}", body);
+        }
+        assert!(server.handle("GET", "/games", "").1["games"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn play_to_a_win() {
+        let server = server();
+        let id = post_board(&server);
+        assert_eq!(server.handle("GET", &format!("/games/{}/result", id), "").0, 409);
+
+        let (status, view) = act(&server, id, "reveal", 3, 3);
+        assert_eq!((status, view["state"].clone()), (200, json!("playing")));
+        let (status, view) = act(&server, id, "flag", 0, 0);
+        assert_eq!((status, view["state"].clone()), (200, json!("won")));
+
+        let (status, result) = server.handle("GET", &format!("/games/{}/result", id), "");
+        assert_eq!((status, result["state"].clone(), result["moves"].clone()), (200, json!("won"), json!(2)));
+        assert_eq!(act(&server, id, "reveal", 1, 1).0, 409);
+
+        let (_, replay) = server.handle("GET", &format!("/games/{}/replay", id), "");
+        assert_eq!(replay["mines"][0], json!([1, 0, 0, 0]));
+    }
+
+    #[test]
+    fn action_errors() {
+        let server = server();
+        let id = post_board(&server);
+        assert_eq!(act(&server, id, "jump", 0, 0).0, 400);
+        assert_eq!(act(&server, id, "reveal", 9, 0).0, 400);
+        assert_eq!(act(&server, id + 1, "reveal", 0, 0).0, 404);
+        assert_eq!(server.handle("GET", "/nothing", "").0, 404);
+
+        //the mines show up once the game is lost
+        let (_, replay) = server.handle("GET", &format!("/games/{}/replay", id), "");
+        assert!(replay["mines"].is_null());
+        assert_eq!(act(&server, id, "reveal", 0, 0).1["state"], "lost");
+        assert_eq!(server.handle("DELETE", &format!("/games/{}", id), "").0, 200);
+        assert_eq!(server.handle("GET", &format!("/games/{}", id), "").0, 404);
+    }
+}
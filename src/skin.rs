@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use egui::{Color32, Rect, Vec2};
+
+//name of the bundled skin
+pub const DEFAULT_SKIN: &str = "default";
+const DEFAULT_PNG: &[u8] = include_bytes!("../assets/skin.png");
+
+//a skin tile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    Covered,
+    Number(u8), //a selected cell with 0..8 neighbour mines
+    Flag,
+    Mine,
+    Exploded, //the mine we got killed on
+    WrongFlag,
+    Smile,
+    Winner,
+    Looser,
+}
+
+//how the tiles are arranged in the sprite sheet
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+    //17 square tiles in a row: covered, 0 to 8, flag, mine,
+    //exploded mine, wrong flag, smile, winner and looser
+    Strip,
+    //the bitmaps of the original windows game, as used by most community skins:
+    //a column of 16 square cell tiles (covered, flag, question mark,
+    //exploded mine, wrong flag, mine, pressed question mark, 8 down to 0)
+    //next to a column of 5 face tiles one and a half times as wide
+    //(pressed, winner, looser, surprised, smile)
+    Winmine,
+}
+
+//a sprite sheet loaded as a texture
+pub struct Skin {
+    pub name: String, //skin file name without the extension
+    texture: egui::TextureHandle,
+    layout: Layout,
+}
+
+//directory with the skin files, in the app config directory
+pub fn skins_dir(app_id: &str) -> Option<PathBuf> {
+    eframe::storage_dir(app_id).map(|d| d.join("skins"))
+}
+
+//names of the *.png files in the skins directory, sorted
+pub fn skin_names(dir: Option<&Path>) -> Vec<String> {
+    let Some(entries) = dir.and_then(|d| std::fs::read_dir(d).ok()) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "png"))
+        .map(|p| p.file_stem().unwrap_or_default().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+// decode a png file into an image
+fn decode_png(bytes: &[u8]) -> Result<egui::ColorImage, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    let data = &buf[..info.buffer_size()];
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => data.to_vec(),
+        png::ColorType::Rgb => data.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => data.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+        png::ColorType::Indexed => return Err(String::from("unsupported indexed colors")),
+    };
+
+    let size = [info.width as usize, info.height as usize];
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, &rgba))
+}
+
+impl Skin {
+    // load the bundled skin or a skin file from dir
+    pub fn open(ctx: &egui::Context, name: &str, dir: Option<&Path>) -> Result<Self, String> {
+        if name == DEFAULT_SKIN {
+            return Self::load(ctx, name, DEFAULT_PNG);
+        }
+
+        let dir = dir.ok_or("there is no config directory")?;
+        let bytes = std::fs::read(dir.join(format!("{}.png", name)))
+            .map_err(|e| format!("{}.png: {}", name, e))?;
+        Self::load(ctx, name, &bytes).map_err(|e| format!("{}.png: {}", name, e))
+    }
+
+    // load a skin from the bytes of a png sprite sheet
+    pub fn load(ctx: &egui::Context, name: &str, bytes: &[u8]) -> Result<Self, String> {
+        let image = decode_png(bytes)?;
+        let [w, h] = image.size;
+
+        let layout = if w > 0 && w == 17 * h {
+            Layout::Strip
+        } else if h > 0 && h % 16 == 0 && w * 32 == h * 5 {
+            Layout::Winmine
+        } else {
+            return Err(format!("a {}x{} image isn't a skin, it should be 17 tiles in a row \
+                or a column of 16 cells next to a column of 5 faces", w, h));
+        };
+
+        //pixel art is scaled without smoothing
+        let texture = ctx.load_texture(format!("skin-{}", name), image, egui::TextureOptions::NEAREST);
+
+        Ok(Self { name: name.to_string(), texture, layout })
+    }
+
+    // texture coordinates of the tile
+    fn uv(&self, tile: Tile) -> Rect {
+        match self.layout {
+            Layout::Strip => {
+                let i = match tile {
+                    Tile::Covered => 0,
+                    Tile::Number(val) => 1 + usize::from(val.min(8)),
+                    Tile::Flag => 10,
+                    Tile::Mine => 11,
+                    Tile::Exploded => 12,
+                    Tile::WrongFlag => 13,
+                    Tile::Smile => 14,
+                    Tile::Winner => 15,
+                    Tile::Looser => 16,
+                };
+                let w = 1. / 17.;
+                Rect::from_min_size(egui::pos2(i as f32 * w, 0.), Vec2::new(w, 1.))
+            },
+            Layout::Winmine => {
+                //cells are 2/5 of the width, faces the other 3/5
+                let cell = |i: usize| Rect::from_min_size(
+                    egui::pos2(0., i as f32 / 16.), Vec2::new(0.4, 1. / 16.));
+                let face = |i: usize| Rect::from_min_size(
+                    egui::pos2(0.4, i as f32 * 0.6 / 6.4), Vec2::new(0.6, 0.6 / 6.4));
+                match tile {
+                    Tile::Covered => cell(0),
+                    Tile::Flag => cell(1),
+                    Tile::Exploded => cell(3),
+                    Tile::WrongFlag => cell(4),
+                    Tile::Mine => cell(5),
+                    Tile::Number(val) => cell(15 - usize::from(val.min(8))),
+                    Tile::Winner => face(1),
+                    Tile::Looser => face(2),
+                    Tile::Smile => face(4),
+                }
+            },
+        }
+    }
+
+    // paint the tile filling rect
+    pub fn paint(&self, painter: &egui::Painter, rect: Rect, tile: Tile) {
+        painter.image(self.texture.id(), rect, self.uv(tile), Color32::WHITE);
+    }
+
+    // the tile as an image widget of the given size
+    pub fn image(&self, tile: Tile, size: Vec2) -> egui::Image<'static> {
+        egui::Image::from_texture((self.texture.id(), size)).uv(self.uv(tile))
+    }
+}
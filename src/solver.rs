@@ -0,0 +1,230 @@
+//logic solver: what the revealed numbers tell about the covered cells,
+//without guessing
+//a number is the sum of the mines around it, so the rules hold with
+//multi-mine cells too: a number with no mines left around it frees its
+//covered neighbours, a number that needs all of them full fills them,
+//and when the covered neighbours of a number are among the ones of another
+//the difference holds the difference of their mines
+use std::collections::HashMap;
+
+use crate::engine::{CellTag, Game, Move};
+
+//the global constraint is only compared to the others
+//when this few cells are left covered
+const ENDGAME_CELLS: usize = 64;
+
+//what the solver can tell without guessing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deduction {
+    pub safe: Vec<usize>, //covered cells without mines
+    pub mines: Vec<(usize, u8)>, //covered cells and their mines
+}
+
+//covered cells holding a known total of mines
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Constraint {
+    cells: Vec<usize>, //sorted cell indexes
+    mines: usize,
+}
+
+// the constraints of the revealed numbers on the unknown cells,
+// and the one of the mines left on all of them
+fn constraints(game: &Game, known: &[Option<u8>]) -> Vec<Constraint> {
+    let mut list = Vec::new();
+    for (ind, cell) in game.map.iter().enumerate() {
+        if cell.flag != CellTag::SELECTED || cell.mines > 0 {
+            continue;
+        }
+
+        let mut cells = Vec::new();
+        let mut mines = usize::from(cell.val);
+        for i in game.neighbours(ind) {
+            match known[i] {
+                Some(m) => mines = mines.saturating_sub(usize::from(m)),
+                None => cells.push(i),
+            }
+        }
+        if !cells.is_empty() {
+            list.push(Constraint { cells, mines });
+        }
+    }
+
+    let cells: Vec<usize> = (0..known.len()).filter(|&i| known[i].is_none()).collect();
+    if !cells.is_empty() {
+        let found: usize = known.iter().flatten().map(|&m| usize::from(m)).sum();
+        list.push(Constraint { cells, mines: game.n_mines.saturating_sub(found) });
+    }
+
+    list.sort();
+    list.dedup();
+    list
+}
+
+// the cells a constraint decides, true if some were unknown
+fn settle(constraint: &Constraint, max_mines: usize, known: &mut [Option<u8>]) -> bool {
+    let cells = constraint.cells.len();
+    let mines = match constraint.mines {
+        0 => 0,
+        m if m == max_mines * cells => max_mines,
+        m if cells == 1 => m,
+        _ => return false,
+    };
+
+    let mut found = false;
+    for &i in &constraint.cells {
+        if known[i].is_none() {
+            known[i] = Some(mines as u8);
+            found = true;
+        }
+    }
+    found
+}
+
+// the safe cells and the mines the revealed numbers tell
+pub fn deduce(game: &Game) -> Deduction {
+    if game.state != 0 {
+        return Deduction::default();
+    }
+
+    //the solver doesn't trust the flags, only the revealed cells
+    let mut known: Vec<Option<u8>> = game.map.iter()
+        .map(|cell| match cell.flag {
+            CellTag::UNSELECTED | CellTag::FLAGGED => None,
+            _ => Some(cell.mines),
+        })
+        .collect();
+    let covered: Vec<usize> = (0..known.len()).filter(|&i| known[i].is_none()).collect();
+    let max_mines = usize::from(game.max_mines);
+
+    loop {
+        let list = constraints(game, &known);
+
+        //the constraints holding each cell
+        let mut by_cell: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (n, constraint) in list.iter().enumerate() {
+            if constraint.cells.len() <= ENDGAME_CELLS {
+                for &i in &constraint.cells {
+                    by_cell.entry(i).or_default().push(n);
+                }
+            }
+        }
+
+        //a constraint inside another one leaves the difference
+        let mut derived = Vec::new();
+        for a in &list {
+            for &n in by_cell.get(&a.cells[0]).into_iter().flatten() {
+                let b = &list[n];
+                if b.cells.len() <= a.cells.len() || b.mines < a.mines
+                    || !a.cells.iter().all(|i| b.cells.binary_search(i).is_ok()) {
+                    continue;
+                }
+                let cells = b.cells.iter().copied().filter(|i| a.cells.binary_search(i).is_err()).collect();
+                derived.push(Constraint { cells, mines: b.mines - a.mines });
+            }
+        }
+
+        let mut found = false;
+        for constraint in list.iter().chain(&derived) {
+            found |= settle(constraint, max_mines, &mut known);
+        }
+        if !found {
+            break;
+        }
+    }
+
+    let mut deduction = Deduction::default();
+    for i in covered {
+        match known[i] {
+            Some(0) => deduction.safe.push(i),
+            Some(m) => deduction.mines.push((i, m)),
+            None => {},
+        }
+    }
+    deduction
+}
+
+// all the free cells are revealed
+pub fn cleared(game: &Game) -> bool {
+    game.state != 2 && game.map.iter()
+        .all(|cell| cell.mines > 0 || matches!(cell.flag, CellTag::SELECTED | CellTag::HOLE))
+}
+
+// reveal the safe cells until the board is cleared or a guess is needed,
+// the game returned is where the solver stopped
+pub fn solve(game: &Game) -> Game {
+    let mut game = game.clone();
+    game.practice = false;
+
+    loop {
+        let deduction = deduce(&game);
+        if deduction.safe.is_empty() {
+            return game;
+        }
+
+        for ind in deduction.safe {
+            if game.map[ind].flag != CellTag::SELECTED {
+                game.apply(Move::Reveal(ind));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::parse_board;
+
+    fn board(text: &str) -> Game {
+        parse_board(text).expect("a valid board")
+    }
+
+    #[test]
+    fn subset_rule() {
+        //1-2-1: each 1 is inside the 2, the difference holds a mine
+        let deduction = deduce(&board(".*.*.\nooooo\n"));
+        assert_eq!(deduction.safe, [0, 2, 4]);
+        assert_eq!(deduction.mines, [(1, 1), (3, 1)]);
+    }
+
+    #[test]
+    fn global_count_rule() {
+        //the 1 holds the only mine, the cell away from it is free
+        let deduction = deduce(&board(".*..\n-o--\n"));
+        assert_eq!(deduction.safe, [3]);
+        assert!(deduction.mines.is_empty());
+
+        //the 1 holds one of the 3 mines, the two cells away from it the others
+        let deduction = deduce(&board("*.-**\no----\n"));
+        assert_eq!(deduction.mines, [(3, 1), (4, 1)]);
+        assert!(deduction.safe.is_empty());
+    }
+
+    #[test]
+    fn guess_only_board() {
+        //a 50/50 at the end of a corridor
+        let game = board("*.\n.o\n..\n");
+        assert_eq!(deduce(&game), Deduction::default());
+        let solved = solve(&game);
+        assert!(!cleared(&solved));
+        assert_eq!(solved.selected, game.selected);
+    }
+
+    #[test]
+    fn solves_from_an_opening() {
+        let solved = solve(&board("*...\n....\n....\n...o\n"));
+        assert!(cleared(&solved));
+        assert_eq!(solved.state, 0);
+    }
+
+    #[test]
+    fn multi_mine_cells() {
+        //the 2 has a single covered neighbour, it holds both mines
+        let game = board("2o.o\n");
+        assert_eq!(game.max_mines, 2);
+        assert_eq!(deduce(&game).mines, [(0, 2)]);
+
+        //a 2 over two cells that take 2 mines each can't tell them apart
+        let game = board("2.\noo\n");
+        assert_eq!(deduce(&game), Deduction::default());
+    }
+}
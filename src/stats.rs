@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+//finished games kept in the results
+const MAX_GAMES: usize = 100;
+
+//a finished game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameResult {
+    pub board: String, //board description
+    pub won: bool,
+    pub time: f64, //game time in seconds
+    pub undo: bool, //moves were undone, it can't be a record
+}
+
+//an endless run, over at its first mine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndlessRun {
+    pub density: f64,
+    pub cleared: usize, //cells selected, the score
+    pub time: f64, //run time in seconds
+}
+
+//the ranked attempt of a daily challenge
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Daily {
+    pub won: bool,
+    pub time: Option<f64>, //game time in seconds, None while playing or given up
+}
+
+//last games and the best times, persisted with the app storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Results {
+    pub games: Vec<GameResult>, //last games, oldest first
+    pub records: HashMap<String, f64>, //fastest win by board
+    pub daily: BTreeMap<String, Daily>, //ranked daily challenges by date
+    pub puzzles: BTreeMap<String, u8>, //best stars by "pack: puzzle"
+    pub campaign: BTreeSet<String>, //passed campaign levels
+    pub endless: Vec<EndlessRun>, //last endless runs, oldest first
+}
+
+impl Results {
+    // add a finished game, games with undone moves
+    // are kept out of the records
+    pub fn add(&mut self, game: GameResult) {
+        let record = game.won && !game.undo
+            && self.records.get(&game.board).is_none_or(|&t| game.time < t);
+        if record {
+            self.records.insert(game.board.clone(), game.time);
+        }
+
+        self.games.push(game);
+        if self.games.len() > MAX_GAMES {
+            self.games.remove(0);
+        }
+    }
+
+    // the ranked attempt of the day starts, leaving it counts as a loss
+    pub fn start_daily(&mut self, date: &str) {
+        self.daily.insert(date.to_string(), Daily::default());
+    }
+
+    pub fn finish_daily(&mut self, date: &str, won: bool, time: f64) {
+        self.daily.insert(date.to_string(), Daily { won, time: Some(time) });
+    }
+
+    pub fn add_endless(&mut self, run: EndlessRun) {
+        self.endless.push(run);
+        if self.endless.len() > MAX_GAMES {
+            self.endless.remove(0);
+        }
+    }
+
+    // keep the best stars of a puzzle
+    pub fn add_puzzle(&mut self, name: &str, stars: u8) {
+        let best = self.puzzles.entry(name.to_string()).or_default();
+        *best = stars.max(*best);
+    }
+
+    // forget the games and the records, the ranked daily attempts stay:
+    // a cleared day would give another one, and the campaign progress
+    // isn't a result
+    pub fn clear(&mut self) {
+        *self = Self {
+            daily: std::mem::take(&mut self.daily),
+            campaign: std::mem::take(&mut self.campaign),
+            ..Self::default()
+        };
+    }
+
+    // results window, returns false when it's closed
+    pub fn ui(&mut self, ctx: &egui::Context) -> bool {
+        let mut open = true;
+
+        egui::Window::new("Results")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Records:");
+                let mut records: Vec<(&String, &f64)> = self.records.iter().collect();
+                records.sort_by(|a, b| a.0.cmp(b.0));
+                egui::Grid::new("records").striped(true).show(ui, |ui| {
+                    for (board, time) in records {
+                        ui.label(board);
+                        ui.label(format!("{:.1}s", time));
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                ui.label("Daily challenges:");
+                egui::Grid::new("daily").striped(true).show(ui, |ui| {
+                    for (date, daily) in self.daily.iter().rev().take(10) {
+                        ui.label(date);
+                        ui.label(match daily.time {
+                            Some(time) if daily.won => format!("won in {:.1}s", time),
+                            Some(_) => String::from("lost"),
+                            None => String::from("not finished"),
+                        });
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                let solved = self.puzzles.values().filter(|&&n| n > 0).count();
+                let stars: u32 = self.puzzles.values().map(|&n| u32::from(n)).sum();
+                ui.label(format!("Puzzles: {} solved, {} stars", solved, stars));
+
+                ui.separator();
+                let best = self.endless.iter().map(|run| run.cleared).max().unwrap_or_default();
+                ui.label(format!("Endless runs: best {} cells", best));
+                egui::Grid::new("endless").striped(true).show(ui, |ui| {
+                    for run in self.endless.iter().rev().take(10) {
+                        ui.label(format!("density {:.2}", run.density));
+                        ui.label(format!("{} cells", run.cleared));
+                        ui.label(format!("{:.1}s", run.time));
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                ui.label("Last games:");
+                egui::ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+                    egui::Grid::new("games").striped(true).show(ui, |ui| {
+                        for game in self.games.iter().rev() {
+                            ui.label(&game.board);
+                            ui.label(if game.won { "won" } else { "lost" });
+                            ui.label(format!("{:.1}s", game.time));
+                            ui.label(if game.undo { "with undo" } else { "" });
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                if ui.button("Clear").clicked() {
+                    self.clear();
+                }
+            });
+
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_keeps_the_daily_attempts_and_the_campaign() {
+        let mut results = Results::default();
+        results.add(GameResult { board: String::from("9x9"), won: true, time: 12., undo: false });
+        results.start_daily("2026-10-19");
+        results.campaign.insert(String::from("First steps"));
+        results.clear();
+        assert!(results.games.is_empty() && results.records.is_empty());
+        assert!(results.daily.contains_key("2026-10-19"));
+        assert!(results.campaign.contains("First steps"));
+    }
+}
@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+use eframe::egui;
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+/// The colors for a catppuccin theme variant.
+/// from https://github.com/catppuccin/egui
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct MyTheme {
+    pub rosewater: Color32,
+    pub flamingo: Color32,
+    pub pink: Color32,
+    pub mauve: Color32,
+    pub red: Color32,
+    pub maroon: Color32,
+    pub peach: Color32,
+    pub yellow: Color32,
+    pub green: Color32,
+    pub teal: Color32,
+    pub sky: Color32,
+    pub sapphire: Color32,
+    pub blue: Color32,
+    pub lavender: Color32,
+    pub text: Color32,
+    pub subtext1: Color32,
+    pub subtext0: Color32,
+    pub overlay2: Color32,
+    pub overlay1: Color32,
+    pub overlay0: Color32,
+    pub surface2: Color32,
+    pub surface1: Color32,
+    pub surface0: Color32,
+    pub base: Color32,
+    pub mantle: Color32,
+    pub crust: Color32,
+    pub black: Color32, //glyphs, the strongest contrast with base
+}
+//Latte colors from catppuccin
+pub const LATTE: MyTheme = MyTheme {
+    rosewater: Color32::from_rgb(220, 138, 120),
+    flamingo: Color32::from_rgb(221, 120, 120),
+    pink: Color32::from_rgb(234, 118, 203),
+    mauve: Color32::from_rgb(136, 57, 239),
+    red: Color32::from_rgb(210, 15, 57),
+    maroon: Color32::from_rgb(230, 69, 83),
+    peach: Color32::from_rgb(254, 100, 11),
+    yellow: Color32::from_rgb(223, 142, 29),
+    green: Color32::from_rgb(64, 160, 43),
+    teal: Color32::from_rgb(23, 146, 153),
+    sky: Color32::from_rgb(4, 165, 229),
+    sapphire: Color32::from_rgb(32, 159, 181),
+    blue: Color32::from_rgb(30, 102, 245),
+    lavender: Color32::from_rgb(114, 135, 253),
+    text: Color32::from_rgb(76, 79, 105),
+    subtext1: Color32::from_rgb(92, 95, 119),
+    subtext0: Color32::from_rgb(108, 111, 133),
+    overlay2: Color32::from_rgb(124, 127, 147),
+    overlay1: Color32::from_rgb(140, 143, 161),
+    overlay0: Color32::from_rgb(156, 160, 176),
+    surface2: Color32::from_rgb(172, 176, 190),
+    surface1: Color32::from_rgb(188, 192, 204),
+    surface0: Color32::from_rgb(204, 208, 218),
+    base: Color32::from_rgb(239, 241, 245),
+    mantle: Color32::from_rgb(230, 233, 239),
+    crust: Color32::from_rgb(220, 224, 232),
+    black: Color32::from_rgb(0, 0, 0),
+};
+
+//Frappé colors from catppuccin
+pub const FRAPPE: MyTheme = MyTheme {
+    rosewater: Color32::from_rgb(242, 213, 207),
+    flamingo: Color32::from_rgb(238, 190, 190),
+    pink: Color32::from_rgb(244, 184, 228),
+    mauve: Color32::from_rgb(202, 158, 230),
+    red: Color32::from_rgb(231, 130, 132),
+    maroon: Color32::from_rgb(234, 153, 156),
+    peach: Color32::from_rgb(239, 159, 118),
+    yellow: Color32::from_rgb(229, 200, 144),
+    green: Color32::from_rgb(166, 209, 137),
+    teal: Color32::from_rgb(129, 200, 190),
+    sky: Color32::from_rgb(153, 209, 219),
+    sapphire: Color32::from_rgb(133, 193, 220),
+    blue: Color32::from_rgb(140, 170, 238),
+    lavender: Color32::from_rgb(186, 187, 241),
+    text: Color32::from_rgb(198, 208, 245),
+    subtext1: Color32::from_rgb(181, 191, 226),
+    subtext0: Color32::from_rgb(165, 173, 206),
+    overlay2: Color32::from_rgb(148, 156, 187),
+    overlay1: Color32::from_rgb(131, 139, 167),
+    overlay0: Color32::from_rgb(115, 121, 148),
+    surface2: Color32::from_rgb(98, 104, 128),
+    surface1: Color32::from_rgb(81, 87, 109),
+    surface0: Color32::from_rgb(65, 69, 89),
+    base: Color32::from_rgb(48, 52, 70),
+    mantle: Color32::from_rgb(41, 44, 60),
+    crust: Color32::from_rgb(35, 38, 52),
+    black: Color32::from_rgb(255, 255, 255),
+};
+
+//Macchiato colors from catppuccin
+pub const MACCHIATO: MyTheme = MyTheme {
+    rosewater: Color32::from_rgb(244, 219, 214),
+    flamingo: Color32::from_rgb(240, 198, 198),
+    pink: Color32::from_rgb(245, 189, 230),
+    mauve: Color32::from_rgb(198, 160, 246),
+    red: Color32::from_rgb(237, 135, 150),
+    maroon: Color32::from_rgb(238, 153, 160),
+    peach: Color32::from_rgb(245, 169, 127),
+    yellow: Color32::from_rgb(238, 212, 159),
+    green: Color32::from_rgb(166, 218, 149),
+    teal: Color32::from_rgb(139, 213, 202),
+    sky: Color32::from_rgb(145, 215, 227),
+    sapphire: Color32::from_rgb(125, 196, 228),
+    blue: Color32::from_rgb(138, 173, 244),
+    lavender: Color32::from_rgb(183, 189, 248),
+    text: Color32::from_rgb(202, 211, 245),
+    subtext1: Color32::from_rgb(184, 192, 224),
+    subtext0: Color32::from_rgb(165, 173, 203),
+    overlay2: Color32::from_rgb(147, 154, 183),
+    overlay1: Color32::from_rgb(128, 135, 162),
+    overlay0: Color32::from_rgb(110, 115, 141),
+    surface2: Color32::from_rgb(91, 96, 120),
+    surface1: Color32::from_rgb(73, 77, 100),
+    surface0: Color32::from_rgb(54, 58, 79),
+    base: Color32::from_rgb(36, 39, 58),
+    mantle: Color32::from_rgb(30, 32, 48),
+    crust: Color32::from_rgb(24, 25, 38),
+    black: Color32::from_rgb(255, 255, 255),
+};
+
+//Mocha colors from catppuccin
+pub const MOCHA: MyTheme = MyTheme {
+    rosewater: Color32::from_rgb(245, 224, 220),
+    flamingo: Color32::from_rgb(242, 205, 205),
+    pink: Color32::from_rgb(245, 194, 231),
+    mauve: Color32::from_rgb(203, 166, 247),
+    red: Color32::from_rgb(243, 139, 168),
+    maroon: Color32::from_rgb(235, 160, 172),
+    peach: Color32::from_rgb(250, 179, 135),
+    yellow: Color32::from_rgb(249, 226, 175),
+    green: Color32::from_rgb(166, 227, 161),
+    teal: Color32::from_rgb(148, 226, 213),
+    sky: Color32::from_rgb(137, 220, 235),
+    sapphire: Color32::from_rgb(116, 199, 236),
+    blue: Color32::from_rgb(137, 180, 250),
+    lavender: Color32::from_rgb(180, 190, 254),
+    text: Color32::from_rgb(205, 214, 244),
+    subtext1: Color32::from_rgb(186, 194, 222),
+    subtext0: Color32::from_rgb(166, 173, 200),
+    overlay2: Color32::from_rgb(147, 153, 178),
+    overlay1: Color32::from_rgb(127, 132, 156),
+    overlay0: Color32::from_rgb(108, 112, 134),
+    surface2: Color32::from_rgb(88, 91, 112),
+    surface1: Color32::from_rgb(69, 71, 90),
+    surface0: Color32::from_rgb(49, 50, 68),
+    base: Color32::from_rgb(30, 30, 46),
+    mantle: Color32::from_rgb(24, 24, 37),
+    crust: Color32::from_rgb(17, 17, 27),
+    black: Color32::from_rgb(255, 255, 255),
+};
+
+impl MyTheme {
+    //dark themes have a dark base
+    pub fn is_dark(&self) -> bool {
+        let [r, g, b, _] = self.base.to_array();
+        (u16::from(r) + u16::from(g) + u16::from(b)) < 384
+    }
+
+    // egui visuals with the theme colors
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut visuals = if self.is_dark() {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+
+        visuals.override_text_color = Some(self.text);
+        visuals.hyperlink_color = self.rosewater;
+        visuals.faint_bg_color = self.surface0;
+        visuals.extreme_bg_color = self.crust;
+        visuals.code_bg_color = self.mantle;
+        visuals.warn_fg_color = self.peach;
+        visuals.error_fg_color = self.maroon;
+        visuals.window_fill = self.base;
+        visuals.panel_fill = self.base;
+        visuals.window_stroke.color = self.overlay1;
+        visuals.widgets.noninteractive.bg_fill = self.base;
+        visuals.widgets.noninteractive.weak_bg_fill = self.base;
+        visuals.widgets.noninteractive.bg_stroke.color = self.overlay1;
+        visuals.widgets.noninteractive.fg_stroke.color = self.text;
+        visuals.widgets.inactive.bg_fill = self.surface0;
+        visuals.widgets.inactive.weak_bg_fill = self.surface0;
+        visuals.widgets.inactive.bg_stroke.color = self.overlay1;
+        visuals.widgets.inactive.fg_stroke.color = self.text;
+        visuals.widgets.active.bg_fill = self.surface1;
+        visuals.widgets.active.weak_bg_fill = self.surface1;
+        visuals.widgets.active.bg_stroke.color = self.overlay1;
+        visuals.widgets.active.fg_stroke.color = self.text;
+        visuals.widgets.hovered.bg_fill = self.surface2;
+        visuals.widgets.hovered.weak_bg_fill = self.surface2;
+        visuals.widgets.hovered.bg_stroke.color = self.overlay1;
+        visuals.widgets.hovered.fg_stroke.color = self.text;
+        visuals.widgets.open.bg_fill = self.surface0;
+        visuals.widgets.open.weak_bg_fill = self.surface0;
+        visuals.widgets.open.bg_stroke.color = self.overlay1;
+        visuals.widgets.open.fg_stroke.color = self.text;
+        visuals.selection.bg_fill = self.blue.linear_multiply(0.4);
+        visuals.selection.stroke.color = self.overlay1;
+        visuals.window_shadow.color = self.base;
+        visuals.popup_shadow.color = self.base;
+        visuals.dark_mode = self.is_dark();
+
+        visuals
+    }
+}
+
+//catppuccin flavours
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Flavour {
+    Latte,
+    Frappe,
+    Macchiato,
+    Mocha,
+}
+
+impl Flavour {
+    pub const ALL: [Flavour; 4] = [Flavour::Latte, Flavour::Frappe, Flavour::Macchiato, Flavour::Mocha];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flavour::Latte => "Latte",
+            Flavour::Frappe => "Frappé",
+            Flavour::Macchiato => "Macchiato",
+            Flavour::Mocha => "Mocha",
+        }
+    }
+
+    pub fn theme(&self) -> MyTheme {
+        match self {
+            Flavour::Latte => LATTE,
+            Flavour::Frappe => FRAPPE,
+            Flavour::Macchiato => MACCHIATO,
+            Flavour::Mocha => MOCHA,
+        }
+    }
+}
+
+//number color palettes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Palette {
+    Catppuccin,
+    Colorblind, //Okabe-Ito colors, safe for color vision deficiencies
+    HighContrast, //saturated dark colors
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Catppuccin, Palette::Colorblind, Palette::HighContrast];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Catppuccin => "catppuccin",
+            Palette::Colorblind => "colorblind safe",
+            Palette::HighContrast => "high contrast",
+        }
+    }
+
+    //color for the number of neighbour mines
+    pub fn color(&self, val: u8, theme: &MyTheme) -> Color32 {
+        match self {
+            Palette::Catppuccin => match val {
+                1 => theme.blue,
+                2 => theme.green,
+                3 => theme.mauve,
+                4 => theme.maroon,
+                5 => theme.sapphire,
+                6 => theme.flamingo,
+                7 => theme.lavender,
+                _ => theme.text,
+            },
+            Palette::Colorblind => match val {
+                1 => Color32::from_rgb(0, 114, 178),
+                2 => Color32::from_rgb(0, 158, 115),
+                3 => Color32::from_rgb(213, 94, 0),
+                4 => Color32::from_rgb(204, 121, 167),
+                5 => Color32::from_rgb(230, 159, 0),
+                6 => Color32::from_rgb(86, 180, 233),
+                7 => theme.black,
+                _ => theme.subtext0,
+            },
+            //lighter colors on the dark flavours
+            Palette::HighContrast if theme.is_dark() => match val {
+                1 => Color32::from_rgb(100, 170, 255),
+                2 => Color32::from_rgb(80, 220, 80),
+                3 => Color32::from_rgb(255, 90, 90),
+                4 => Color32::from_rgb(200, 140, 255),
+                5 => Color32::from_rgb(255, 170, 60),
+                6 => Color32::from_rgb(60, 220, 220),
+                7 => Color32::from_rgb(255, 255, 255),
+                _ => Color32::from_rgb(200, 200, 200),
+            },
+            Palette::HighContrast => match val {
+                1 => Color32::from_rgb(0, 0, 255),
+                2 => Color32::from_rgb(0, 110, 0),
+                3 => Color32::from_rgb(220, 0, 0),
+                4 => Color32::from_rgb(0, 0, 128),
+                5 => Color32::from_rgb(128, 0, 0),
+                6 => Color32::from_rgb(0, 110, 110),
+                7 => Color32::from_rgb(0, 0, 0),
+                _ => Color32::from_rgb(64, 64, 64),
+            },
+        }
+    }
+}
+
+const UTF8_SMILE: [u8; 4] = [0xF0, 0x9F, 0x98, 0x90];
+const UTF8_WINNER: [u8; 4] = [0xF0, 0x9F, 0x98, 0x8E];
+const UTF8_LOOSER: [u8; 4] = [0xF0, 0x9F, 0x98, 0x9E];
+const UTF8_BOMB: [u8; 4] = [0xF0, 0x9F, 0x92, 0xA3];
+const UTF8_FLAG: [u8; 4] = [0xF0,0x9F,0x9A,0xA9];
+const UTF8_CROSS: [u8; 4] = [0xF0, 0x9F, 0x8E, 0x8C];
+const UTF8_DEAD: [u8; 4] = [0xF0, 0x9F, 0x95, 0xB1];
+//const UTF8_CHECK: [u8; 4] = [0x00, 0xE2, 0x9C, 0x85];
+
+//text drawn for the cells and the smiley
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyphs {
+    pub flag: String,
+    pub bomb: String,
+    pub dead: String, //the mine we got killed on
+    pub cross: String, //a wrong flag
+    pub smile: String,
+    pub winner: String,
+    pub looser: String,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        let utf8 = |bytes: [u8; 4]| String::from_utf8(bytes.to_vec()).unwrap();
+        Self {
+            flag: utf8(UTF8_FLAG),
+            bomb: utf8(UTF8_BOMB),
+            dead: utf8(UTF8_DEAD),
+            cross: utf8(UTF8_CROSS),
+            smile: utf8(UTF8_SMILE),
+            winner: utf8(UTF8_WINNER),
+            looser: utf8(UTF8_LOOSER),
+        }
+    }
+}
+
+//what the board looks like: theme, number colors and glyphs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Look {
+    pub theme: MyTheme,
+    pub numbers: [Color32; 9], //colors for 0..8 neighbour mines
+    pub glyphs: Glyphs,
+}
+
+impl Look {
+    pub fn new(theme: MyTheme, palette: Palette) -> Self {
+        Self {
+            theme,
+            numbers: std::array::from_fn(|val| palette.color(val as u8, &theme)),
+            glyphs: Glyphs::default(),
+        }
+    }
+
+    //color for the number of neighbour mines
+    pub fn number(&self, val: u8) -> Color32 {
+        self.numbers.get(usize::from(val)).copied().unwrap_or(self.theme.text)
+    }
+}
+
+impl MyTheme {
+    // every color slot with its name in the theme files
+    pub fn slots_mut(&mut self) -> [(&'static str, &mut Color32); 27] {
+        [
+            ("rosewater", &mut self.rosewater),
+            ("flamingo", &mut self.flamingo),
+            ("pink", &mut self.pink),
+            ("mauve", &mut self.mauve),
+            ("red", &mut self.red),
+            ("maroon", &mut self.maroon),
+            ("peach", &mut self.peach),
+            ("yellow", &mut self.yellow),
+            ("green", &mut self.green),
+            ("teal", &mut self.teal),
+            ("sky", &mut self.sky),
+            ("sapphire", &mut self.sapphire),
+            ("blue", &mut self.blue),
+            ("lavender", &mut self.lavender),
+            ("text", &mut self.text),
+            ("subtext1", &mut self.subtext1),
+            ("subtext0", &mut self.subtext0),
+            ("overlay2", &mut self.overlay2),
+            ("overlay1", &mut self.overlay1),
+            ("overlay0", &mut self.overlay0),
+            ("surface2", &mut self.surface2),
+            ("surface1", &mut self.surface1),
+            ("surface0", &mut self.surface0),
+            ("base", &mut self.base),
+            ("mantle", &mut self.mantle),
+            ("crust", &mut self.crust),
+            ("black", &mut self.black),
+        ]
+    }
+}
+
+//a theme file:
+//  [colors]   every MyTheme slot, "#rrggbb"
+//  [numbers]  optional, "1" to "8", "#rrggbb"
+//  [glyphs]   optional, flag, bomb, dead, cross, smile, winner and looser
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeToml {
+    colors: HashMap<String, String>,
+    #[serde(default)]
+    numbers: HashMap<String, String>,
+    #[serde(default)]
+    glyphs: GlyphsToml,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct GlyphsToml {
+    flag: Option<String>,
+    bomb: Option<String>,
+    dead: Option<String>,
+    cross: Option<String>,
+    smile: Option<String>,
+    winner: Option<String>,
+    looser: Option<String>,
+}
+
+//a theme loaded from a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomTheme {
+    pub theme: MyTheme,
+    pub numbers: [Option<Color32>; 9], //number colors set in the file
+    pub glyphs: Glyphs,
+}
+
+impl CustomTheme {
+    // the look with the palette for the numbers the file doesn't set
+    pub fn look(&self, palette: Palette) -> Look {
+        let mut look = Look::new(self.theme, palette);
+        for (color, custom) in look.numbers.iter_mut().zip(self.numbers) {
+            if let Some(custom) = custom {
+                *color = custom;
+            }
+        }
+        look.glyphs = self.glyphs.clone();
+        look
+    }
+}
+
+// "#rrggbb" or "#rrggbbaa"
+fn parse_color(s: &str) -> Option<Color32> {
+    let hex = s.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) {
+        return None;
+    }
+
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let (r, g, b) = (byte(0)?, byte(2)?, byte(4)?);
+    let a = if hex.len() == 8 { byte(6)? } else { 255 };
+    Some(Color32::from_rgba_unmultiplied(r, g, b, a))
+}
+
+// parse the text of a theme file
+pub fn parse_theme(text: &str) -> Result<CustomTheme, String> {
+    let file: ThemeToml = toml::from_str(text).map_err(|e| e.to_string())?;
+
+    let mut theme = LATTE;
+    let mut known = Vec::new();
+    for (name, slot) in theme.slots_mut() {
+        let value = file.colors.get(name)
+            .ok_or_else(|| format!("[colors] is missing '{}'", name))?;
+        *slot = parse_color(value)
+            .ok_or_else(|| format!("[colors] {} = '{}' is not a #rrggbb color", name, value))?;
+        known.push(name);
+    }
+    if let Some(name) = file.colors.keys().find(|k| !known.contains(&k.as_str())) {
+        return Err(format!("[colors] has an unknown color '{}'", name));
+    }
+
+    let mut numbers = [None; 9];
+    for (key, value) in &file.numbers {
+        let val = key.parse::<usize>().ok().filter(|v| (1..=8).contains(v))
+            .ok_or_else(|| format!("[numbers] '{}' is not a number from 1 to 8", key))?;
+        numbers[val] = Some(parse_color(value)
+            .ok_or_else(|| format!("[numbers] {} = '{}' is not a #rrggbb color", key, value))?);
+    }
+
+    let mut glyphs = Glyphs::default();
+    for (glyph, custom, name) in [
+        (&mut glyphs.flag, file.glyphs.flag, "flag"),
+        (&mut glyphs.bomb, file.glyphs.bomb, "bomb"),
+        (&mut glyphs.dead, file.glyphs.dead, "dead"),
+        (&mut glyphs.cross, file.glyphs.cross, "cross"),
+        (&mut glyphs.smile, file.glyphs.smile, "smile"),
+        (&mut glyphs.winner, file.glyphs.winner, "winner"),
+        (&mut glyphs.looser, file.glyphs.looser, "looser"),
+    ] {
+        match custom {
+            Some(s) if s.is_empty() => return Err(format!("[glyphs] {} is empty", name)),
+            Some(s) => *glyph = s,
+            None => {},
+        }
+    }
+
+    Ok(CustomTheme { theme, numbers, glyphs })
+}
+
+// the text of a theme file for a look
+pub fn theme_text(look: &Look) -> String {
+    let hex = |c: Color32| format!("\"#{:02x}{:02x}{:02x}\"", c.r(), c.g(), c.b());
+
+    let mut text = String::from("[colors]\n");
+    let mut theme = look.theme;
+    for (name, color) in theme.slots_mut() {
+        text += &format!("{} = {}\n", name, hex(*color));
+    }
+
+    text += "\n[numbers]\n";
+    for (val, color) in look.numbers.iter().enumerate().skip(1) {
+        text += &format!("{} = {}\n", val, hex(*color));
+    }
+
+    let g = &look.glyphs;
+    text += "\n[glyphs]\n";
+    for (name, glyph) in [("flag", &g.flag), ("bomb", &g.bomb), ("dead", &g.dead),
+        ("cross", &g.cross), ("smile", &g.smile), ("winner", &g.winner), ("looser", &g.looser)] {
+        text += &format!("{} = {}\n", name, toml::Value::String(glyph.clone()));
+    }
+
+    text
+}
+
+//a *.toml file in the themes directory
+pub struct ThemeFile {
+    pub name: String, //file name without the extension
+    modified: Option<SystemTime>, //modification time when it was loaded
+    pub theme: Result<CustomTheme, String>, //the theme or why it couldn't be loaded
+}
+
+//the theme files, reloaded when they change
+pub struct ThemeFiles {
+    pub dir: Option<PathBuf>, //themes directory in the app config directory
+    pub files: Vec<ThemeFile>, //sorted by name
+    scanned: Option<Instant>, //last directory scan
+}
+
+impl ThemeFiles {
+    pub fn new(app_id: &str) -> Self {
+        Self {
+            dir: eframe::storage_dir(app_id).map(|d| d.join("themes")),
+            files: Vec::new(),
+            scanned: None,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ThemeFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    // scan the directory at most once a second, loading new
+    // and changed files and dropping the deleted ones
+    pub fn refresh(&mut self) {
+        if self.scanned.is_some_and(|t| t.elapsed() < Duration::from_secs(1)) {
+            return;
+        }
+        self.scanned = Some(Instant::now());
+
+        let Some(entries) = self.dir.as_ref().and_then(|d| std::fs::read_dir(d).ok()) else {
+            self.files.clear();
+            return;
+        };
+
+        let mut files = Vec::new();
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().is_none_or(|e| e != "toml") {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+            //keep the loaded theme if the file didn't change
+            let old = self.files.iter().position(|f| f.name == name && f.modified == modified);
+            let file = match old {
+                Some(i) => self.files.swap_remove(i),
+                None => ThemeFile {
+                    theme: std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| parse_theme(&text)),
+                    name,
+                    modified,
+                },
+            };
+            files.push(file);
+        }
+
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        self.files = files;
+    }
+
+    // write the look to a new theme file, the first free "themeN.toml",
+    // returns its path
+    pub fn export(&mut self, look: &Look) -> Result<PathBuf, String> {
+        let dir = self.dir.as_ref().ok_or("there is no config directory")?;
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+        let path = (1..=1000)
+            .map(|n| dir.join(format!("theme{}.toml", n)))
+            .find(|path| !path.exists())
+            .ok_or("there are too many theme files")?;
+        //create_new: never overwrite a file made since the check
+        let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(&path)
+            .map_err(|e| format!("can't create {}: {}", path.display(), e))?;
+        file.write_all(theme_text(look).as_bytes()).map_err(|e| e.to_string())?;
+        self.scanned = None;
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_text_round_trip() {
+        let mut look = Look::new(MOCHA, Palette::Colorblind);
+        look.glyphs.flag = String::from("\"F\"");
+        look.glyphs.bomb = String::from("\\*\n");
+        let custom = parse_theme(&theme_text(&look)).expect("a valid theme file");
+        //the color of 0 isn't in the file
+        let copy = custom.look(Palette::Catppuccin);
+        assert_eq!(copy.theme, look.theme);
+        assert_eq!(copy.numbers[1..], look.numbers[1..]);
+        assert_eq!(copy.glyphs, look.glyphs);
+    }
+}